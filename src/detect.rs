@@ -0,0 +1,1443 @@
+//! Level-based silence detection over a sliding window of frames.
+
+use crate::source::PcmSample;
+use anyhow::Result;
+use clap::ValueEnum;
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Policy for reducing the channels of an interleaved frame to a single level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelPolicy {
+    /// Use the loudest channel of each frame.
+    Max,
+    /// Use the mean power across all channels of each frame.
+    Mean,
+    /// Use only the given (zero-based) channel.
+    Channel(usize),
+}
+
+impl FromStr for ChannelPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(ChannelPolicy::Max),
+            "mean" => Ok(ChannelPolicy::Mean),
+            other => other.parse::<usize>().map(ChannelPolicy::Channel).map_err(|_| {
+                format!("invalid channel policy '{other}': expected 'max', 'mean' or a channel index")
+            }),
+        }
+    }
+}
+
+impl ChannelPolicy {
+    /// Returns the squared normalized level of a single interleaved frame under this policy.
+    pub fn frame_power<T: PcmSample>(self, frame: &[T], bits_per_sample: u16) -> f64 {
+        let power = |s: T| s.to_normalized(bits_per_sample).powi(2);
+        match self {
+            ChannelPolicy::Max => frame.iter().map(|&s| power(s)).fold(0.0, f64::max),
+            ChannelPolicy::Mean => {
+                frame.iter().map(|&s| power(s)).sum::<f64>() / frame.len() as f64
+            }
+            ChannelPolicy::Channel(ch) => power(frame[ch]),
+        }
+    }
+
+    /// Combines powers measured separately for each channel of one frame under this policy.
+    pub fn combine(self, powers: &[f64]) -> f64 {
+        match self {
+            ChannelPolicy::Max => powers.iter().copied().fold(0.0, f64::max),
+            ChannelPolicy::Mean => powers.iter().sum::<f64>() / powers.len() as f64,
+            ChannelPolicy::Channel(ch) => powers[ch],
+        }
+    }
+}
+
+/// Random access to the per-frame detection power of an audio stream.
+///
+/// Each frame's power is its squared normalized level with channels combined per
+/// [`ChannelPolicy`], so full scale is 1.0 regardless of the sample format.
+pub trait FrameSource {
+    /// Returns the total number of frames.
+    fn frames(&self) -> usize;
+
+    /// Returns the number of interleaved channels per frame.
+    fn channels(&self) -> usize;
+
+    /// Replaces the contents of `out` with the power of each frame in `range`.
+    fn read_powers(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()>;
+
+    /// Replaces the contents of `out` with the interleaved samples of the frames in `range`,
+    /// normalized so that full scale is 1.0 and keeping their sign.
+    fn read_samples(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()>;
+}
+
+/// A way of telling sound from silence: measures the level of any run of frames of a source,
+/// as a linear amplitude where full scale is 1.0, for the scans to compare against their
+/// thresholds.
+///
+/// Scans slide a window through the source a hop at a time, so a detector may keep what it
+/// read for one window to measure the next, overlapping one cheaply. Windows may still be
+/// asked for in any order, and a window's level must not depend on the ones before it.
+pub trait Detector<S: FrameSource + ?Sized> {
+    /// Returns the level of the frames in `range` of `source`.
+    fn level(&mut self, source: &mut S, range: Range<usize>) -> Result<f64>;
+}
+
+/// Converts a duration in milliseconds to a whole number of frames (at least one).
+pub fn ms_to_frames(ms: f64, sample_rate: u32) -> usize {
+    ((ms * sample_rate as f64 / 1000.0).round() as usize).max(1)
+}
+
+/// Converts a duration in milliseconds to a whole number of frames, which may be zero.
+pub fn ms_to_frame_count(ms: f64, sample_rate: u32) -> u64 {
+    (ms * sample_rate as f64 / 1000.0).round() as u64
+}
+
+/// A detection window sliding through a source, remembering where it was so that each move
+/// tells which frames it newly covers: the edge of those frames is where a scan cuts.
+#[derive(Default)]
+struct Window {
+    range: Range<usize>,
+}
+
+impl Window {
+    /// Moves the window to `range` and returns its level as measured by `detector`, together
+    /// with the range of frames it did not cover before.
+    fn move_to<S, D>(
+        &mut self,
+        source: &mut S,
+        detector: &mut D,
+        range: Range<usize>,
+    ) -> Result<(f64, Range<usize>)>
+    where
+        S: FrameSource + ?Sized,
+        D: Detector<S> + ?Sized,
+    {
+        let previous = mem::replace(&mut self.range, range.clone());
+        let kept = previous.start.max(range.start)..previous.end.min(range.end);
+        let added = if kept.is_empty() {
+            range.clone()
+        } else {
+            match (range.start < kept.start, range.end > kept.end) {
+                (true, true) => range.clone(),
+                (true, false) => range.start..kept.start,
+                (false, true) => kept.end..range.end,
+                (false, false) => range.start..range.start,
+            }
+        };
+        Ok((detector.level(source, range)?, added))
+    }
+}
+
+/// Lowest level tracked by the noise floor histogram; quieter windows (including digital
+/// silence) count towards this bin.
+const FLOOR_MIN_DB: f64 = -160.0;
+/// Highest level tracked by the noise floor histogram; float audio may exceed full scale.
+const FLOOR_MAX_DB: f64 = 20.0;
+/// Width of one noise floor histogram bin.
+const FLOOR_BIN_DB: f64 = 0.1;
+
+/// Settings for deriving each file's threshold from its own noise floor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveThreshold {
+    /// Percentile (0-100) of window levels taken as the noise floor.
+    pub percentile: f64,
+    /// How far above the noise floor the threshold is placed, in dB.
+    pub margin_db: f64,
+    /// Lowest threshold allowed, in dBFS.
+    pub min_db: Option<f64>,
+    /// Highest threshold allowed, in dBFS.
+    pub max_db: Option<f64>,
+}
+
+impl AdaptiveThreshold {
+    /// Returns the threshold in dBFS for a file with the given noise floor.
+    pub fn threshold_db(&self, noise_floor_db: f64) -> f64 {
+        let mut threshold = noise_floor_db + self.margin_db;
+        if let Some(min) = self.min_db {
+            threshold = threshold.max(min);
+        }
+        if let Some(max) = self.max_db {
+            threshold = threshold.min(max);
+        }
+        threshold
+    }
+}
+
+/// Estimates the noise floor of `source` in dBFS as the given `percentile` (0-100) of the levels
+/// `detector` measures for its consecutive `window_size`-frame windows.
+///
+/// Levels are collected in a fixed histogram of 0.1 dB bins rather than stored, so the whole
+/// source is read once, one window at a time, in constant memory. Returns `None` for an empty
+/// source.
+pub fn noise_floor_db<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    window_size: usize,
+    percentile: f64,
+) -> Result<Option<f64>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let len = source.frames();
+    let bins = ((FLOOR_MAX_DB - FLOOR_MIN_DB) / FLOOR_BIN_DB) as usize;
+    let mut histogram = vec![0u64; bins];
+    let mut windows = 0;
+    for i in (0..len).step_by(window_size) {
+        let level = detector.level(source, i..(i + window_size).min(len))?;
+        let db = 20.0 * level.log10();
+        // NaN or -inf (digital silence) lands in the lowest bin.
+        let bin = ((db - FLOOR_MIN_DB) / FLOOR_BIN_DB).max(0.0) as usize;
+        histogram[bin.min(bins - 1)] += 1;
+        windows += 1;
+    }
+    if windows == 0 {
+        return Ok(None);
+    }
+
+    let rank = ((percentile / 100.0 * windows as f64).ceil() as u64).clamp(1, windows);
+    let mut seen = 0;
+    for (bin, &count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Ok(Some(FLOOR_MIN_DB + (bin as f64 + 0.5) * FLOOR_BIN_DB));
+        }
+    }
+    unreachable!("rank is at most the number of windows")
+}
+
+/// Level of the signal that a relative threshold is measured against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ThresholdReference {
+    /// The loudest single frame, with channels combined per [`ChannelPolicy`].
+    #[default]
+    Peak,
+    /// The loudest detection window, by the level the detector measures.
+    LoudestWindow,
+}
+
+/// Measures the reference level of `source` in dBFS by reading it once, one `window_size`-frame
+/// window at a time. Returns negative infinity for an empty or digitally silent source.
+pub fn reference_level_db<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    window_size: usize,
+    reference: ThresholdReference,
+) -> Result<f64>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let len = source.frames();
+    let mut powers = Vec::with_capacity(window_size);
+    let mut loudest: f64 = 0.0;
+    for i in (0..len).step_by(window_size) {
+        let range = i..(i + window_size).min(len);
+        let level = match reference {
+            ThresholdReference::Peak => {
+                source.read_powers(range, &mut powers)?;
+                powers.iter().copied().fold(0.0, f64::max).sqrt()
+            }
+            ThresholdReference::LoudestWindow => detector.level(source, range)?,
+        };
+        loudest = loudest.max(level);
+    }
+    Ok(20.0 * loudest.log10())
+}
+
+/// Settings for the window scan in [`trim_samples`], with sizes in frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScanParams {
+    /// Silence threshold for the start search in dBFS (the trigger level, with a `release_db`);
+    /// `None` leaves the start untouched.
+    pub start_db: Option<f64>,
+    /// Silence threshold for the end search in dBFS; `None` leaves the end untouched.
+    pub end_db: Option<f64>,
+    /// Lower level in dBFS that the cut points extend outward to from the triggered windows
+    /// (hysteresis); `None` uses the edge thresholds alone.
+    pub release_db: Option<f64>,
+    /// Frames per detection window.
+    pub window_size: usize,
+    /// Frames the window advances per step; between 1 and `window_size`.
+    pub hop_size: usize,
+    /// Frames in the envelope used to refine cut points; 1 compares individual frames.
+    pub refine_size: usize,
+    /// Frames of loud signal a region must span before it counts as sound; 0 accepts any
+    /// single loud window.
+    pub min_sound: usize,
+    /// Fraction (above 0, at most 1) of the windows across a region that must exceed the
+    /// threshold for it to stay one region; below 1.0 tolerates short dropouts.
+    pub loud_fraction: f64,
+    /// Most frames that may be trimmed from the start; `None` for no limit.
+    pub max_trim_start: Option<usize>,
+    /// Most frames that may be trimmed from the end; `None` for no limit.
+    pub max_trim_end: Option<usize>,
+}
+
+impl ScanParams {
+    /// Creates parameters that trim both edges at `threshold_db`, cutting at the first loud
+    /// window with per-frame refinement.
+    pub fn new(threshold_db: f64, window_size: usize, hop_size: usize) -> Self {
+        Self {
+            start_db: Some(threshold_db),
+            end_db: Some(threshold_db),
+            release_db: None,
+            window_size,
+            hop_size,
+            refine_size: 1,
+            min_sound: 0,
+            loud_fraction: 1.0,
+            max_trim_start: None,
+            max_trim_end: None,
+        }
+    }
+
+    /// Requires sound to last at least `min_sound` frames, with at least `fraction` (0-1) of
+    /// the windows spanning it above the threshold.
+    ///
+    /// The length is measured between the first and last loud frames of the region, as found
+    /// by the refinement envelope, rather than by counting loud windows: every window
+    /// overlapping a loud click is loud, while windows only partly covering a sound just above
+    /// the threshold are not, so window counts overstate the one and understate the other.
+    pub fn with_min_sound(mut self, min_sound: usize, fraction: f64) -> Self {
+        self.min_sound = min_sound;
+        self.loud_fraction = fraction;
+        self
+    }
+}
+
+/// Cut points found by [`trim_samples`], in frames.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cuts {
+    /// Cut points quantized to the detection hop.
+    pub coarse: Range<usize>,
+    /// Sample-accurate cut points found inside the windows that triggered the coarse ones.
+    pub refined: Range<usize>,
+    /// The start cut was held back by [`ScanParams::max_trim_start`].
+    pub capped_start: bool,
+    /// The end cut was held back by [`ScanParams::max_trim_end`].
+    pub capped_end: bool,
+}
+
+impl Cuts {
+    const SILENT: Cuts = Cuts {
+        coarse: 0..0,
+        refined: 0..0,
+        capped_start: false,
+        capped_end: false,
+    };
+
+    /// Limits the silence trimmed from a source of `len` frames to `max_start` frames at the
+    /// start and `max_end` frames at the end. An all-silent source counts as leading silence
+    /// throughout, so a start limit keeps its tail.
+    fn capped(self, len: usize, max_start: Option<usize>, max_end: Option<usize>) -> Self {
+        if max_start.is_none() && max_end.is_none() {
+            return self;
+        }
+        let mut cuts = if self.refined.is_empty() {
+            Cuts {
+                coarse: len..len,
+                refined: len..len,
+                ..self
+            }
+        } else {
+            self
+        };
+        if let Some(max) = max_start
+            && cuts.refined.start > max
+        {
+            cuts.refined.start = max;
+            cuts.coarse.start = cuts.coarse.start.min(max);
+            cuts.capped_start = true;
+        }
+        if let Some(max) = max_end
+            && len - cuts.refined.end > max
+        {
+            cuts.refined.end = len - max;
+            cuts.coarse.end = cuts.coarse.end.max(len - max);
+            cuts.capped_end = true;
+        }
+        if cuts.refined.is_empty() {
+            return Cuts::SILENT;
+        }
+        cuts
+    }
+}
+
+/// A run of loud windows found by [`find_sound`].
+struct Run {
+    /// Coarse cut point: the edge of the hop that made the run's first loud window loud.
+    cut: usize,
+    /// The run's first loud window, searched again during refinement.
+    window: Range<usize>,
+    /// The far edge of the window that completed the run.
+    reach: usize,
+}
+
+/// A run of loud windows that [`find_sound`] is following until it is long enough.
+struct Stretch {
+    /// The run so far, reaching to the latest loud window.
+    run: Run,
+    /// The first loud frame of the run, or one past the last when scanning backward.
+    onset: usize,
+    /// Windows scanned since the run began.
+    windows: usize,
+    /// How many of those were loud.
+    loud: usize,
+}
+
+/// Slides a window through `windows` in order and returns the first run of windows above
+/// `threshold_rms` whose loud signal spans at least `params.min_sound` frames, with at least
+/// `params.loud_fraction` of the windows since the run began loud. `backward` tells which edge
+/// of each window's new frames is the cut, and that frames are counted from the end.
+///
+/// The span runs from the first loud frame of the run's first window to the last loud frame
+/// of its latest window, both found with the refinement envelope of `params.refine_size`
+/// frames; the latter is only searched for once the window reaches far enough to complete the
+/// run.
+fn find_sound<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    windows: impl Iterator<Item = Range<usize>>,
+    params: &ScanParams,
+    threshold_rms: f64,
+    backward: bool,
+) -> Result<Option<Run>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let mut window = Window::default();
+    let mut stretch: Option<Stretch> = None;
+    for range in windows {
+        let (level, added) = window.move_to(source, detector, range.clone())?;
+        let loud = level > threshold_rms;
+        if let Some(s) = &mut stretch {
+            s.windows += 1;
+            s.loud += loud as usize;
+            if (s.loud as f64) < params.loud_fraction * s.windows as f64 {
+                stretch = None;
+            }
+        }
+        if !loud {
+            continue;
+        }
+
+        let cut = if backward { added.end } else { added.start };
+        let reach = if backward { range.start } else { range.end };
+        if params.min_sound == 0 {
+            return Ok(Some(Run {
+                cut,
+                window: range,
+                reach,
+            }));
+        }
+        let s = match &mut stretch {
+            Some(s) => s,
+            None => {
+                let onset = refine(
+                    source,
+                    detector,
+                    range.clone(),
+                    params.refine_size,
+                    threshold_rms,
+                    backward,
+                )?
+                .unwrap_or(cut);
+                stretch.insert(Stretch {
+                    run: Run {
+                        cut,
+                        window: range.clone(),
+                        reach,
+                    },
+                    onset,
+                    windows: 1,
+                    loud: 1,
+                })
+            }
+        };
+        s.run.reach = reach;
+        if reach.abs_diff(s.onset) < params.min_sound {
+            continue;
+        }
+        let last = refine(
+            source,
+            detector,
+            range,
+            params.refine_size,
+            threshold_rms,
+            !backward,
+        )?
+        .unwrap_or(cut);
+        if last.abs_diff(s.onset) >= params.min_sound {
+            return Ok(stretch.map(|s| s.run));
+        }
+    }
+    Ok(None)
+}
+
+/// Finds the frames to keep after trimming leading/trailing silence, using the level that
+/// `detector` measures for a window of `params.window_size` frames that slides through the
+/// source `params.hop_size` frames at a time. The returned ranges are in frames, so
+/// all channels are cut at the same boundary; they are empty if everything is silent.
+///
+/// The coarse start is placed at the first frame of the hop that brought the window above the
+/// start threshold, scanning forward, and the coarse end likewise scanning backward from the
+/// end of the source against the end threshold, so coarse cut points are quantized to the hop
+/// rather than the window length. An edge without a threshold is left where it is.
+/// Loud windows only count once the loud signal they cover lasts long enough (see
+/// [`ScanParams::with_min_sound`]), so isolated clicks in the silence do not stop either scan.
+/// With a `release_db`, each boundary then moves outward one hop at a time for as long as the
+/// window stays above that lower level, keeping soft onsets and decaying tails.
+/// Each cut is then refined by sliding a short envelope of `params.refine_size` frames one
+/// frame at a time across the last window above the edge's threshold (the release level, if
+/// set), and taking the first (or, for the end, last) frame at which the envelope exceeds that
+/// level. Only window-sized buffers are ever held.
+///
+/// Finally, cuts that would remove more than `max_trim_start` or `max_trim_end` frames are
+/// moved back to that limit and flagged in the result.
+///
+/// # Panics
+///
+/// Panics if `hop_size` is zero or larger than `window_size`, if `refine_size` is zero, or if
+/// `loud_fraction` is not above zero and at most one.
+pub fn trim_samples<S, D>(source: &mut S, detector: &mut D, params: &ScanParams) -> Result<Cuts>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let cuts = detect_cuts(source, detector, params)?;
+    Ok(cuts.capped(source.frames(), params.max_trim_start, params.max_trim_end))
+}
+
+/// Runs the window scan and refinement of [`trim_samples`], without the trim limits.
+fn detect_cuts<S, D>(source: &mut S, detector: &mut D, params: &ScanParams) -> Result<Cuts>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let ScanParams {
+        window_size,
+        hop_size,
+        refine_size,
+        ..
+    } = *params;
+    assert!(
+        hop_size > 0 && hop_size <= window_size,
+        "hop size must be between 1 and the window size"
+    );
+    assert!(
+        refine_size > 0,
+        "refinement envelope must be at least one frame"
+    );
+    assert!(
+        params.loud_fraction > 0.0 && params.loud_fraction <= 1.0,
+        "fraction of loud windows must be above 0 and at most 1"
+    );
+    let len = source.frames();
+    if len == 0 {
+        return Ok(Cuts::SILENT);
+    }
+
+    // Levels are computed in the normalized domain, where full scale is 1.0 for every format.
+    let to_rms = |db: f64| 10f64.powf(db / 20.0);
+    let release_rms = params.release_db.map(to_rms);
+
+    // Find start trim point: first run of loud windows.
+    let start = match params.start_db.map(to_rms) {
+        Some(threshold_rms) => {
+            let forward = (0..len)
+                .step_by(hop_size)
+                .map(|i| i..(i + window_size).min(len));
+            match find_sound(source, detector, forward, params, threshold_rms, false)? {
+                Some(run) => Some((run, threshold_rms)),
+                None => return Ok(Cuts::SILENT),
+            }
+        }
+        None => None,
+    };
+    let start_cut = start.as_ref().map_or(0, |(run, _)| run.cut);
+
+    // Find end trim point: last run of loud windows. Windows ending at or before the start
+    // point cannot produce a non-empty result, so the scan stops there. Backward windows are
+    // aligned to the end of the source rather than the start, so if the only sound is the run
+    // that ended the forward scan, the backward scan may stop short of it; that run's far edge
+    // is the end then.
+    let end = match params.end_db.map(to_rms) {
+        Some(threshold_rms) => {
+            let backward = (0..=len)
+                .rev()
+                .step_by(hop_size)
+                .take_while(|&i| i > start_cut)
+                .map(|i| i.saturating_sub(window_size)..i);
+            let run = match find_sound(source, detector, backward, params, threshold_rms, true)? {
+                Some(run) => run,
+                None => match &start {
+                    Some((start, _)) => Run {
+                        cut: start.reach,
+                        window: start.reach.saturating_sub(window_size)..start.reach,
+                        reach: start.cut,
+                    },
+                    None => return Ok(Cuts::SILENT),
+                },
+            };
+            Some((run, threshold_rms))
+        }
+        None => None,
+    };
+    let end_cut = end.as_ref().map_or(len, |(run, _)| run.cut);
+    if start_cut >= end_cut {
+        return Ok(Cuts::SILENT);
+    }
+
+    // With hysteresis, widen each boundary hop by hop while the window stays above the release
+    // level, and refine against that level.
+    let mut coarse = start_cut..end_cut;
+    let mut refined = coarse.clone();
+    if let Some((run, threshold_rms)) = start {
+        let (mut cut, mut window) = (run.cut, run.window);
+        if let Some(release_rms) = release_rms {
+            let earlier = iter::successors(Some(window.start), |&i| {
+                (i > 0).then(|| i.saturating_sub(hop_size))
+            })
+            .map(|i| i..(i + window_size).min(len));
+            if let Some(extended) = extend(source, detector, earlier, release_rms, true)? {
+                (cut, window) = extended;
+            }
+        }
+        let level = release_rms.unwrap_or(threshold_rms);
+        coarse.start = cut;
+        refined.start = refine(source, detector, window, refine_size, level, false)?.unwrap_or(cut);
+    }
+    if let Some((run, threshold_rms)) = end {
+        let (mut cut, mut window) = (run.cut, run.window);
+        if let Some(release_rms) = release_rms {
+            let later = iter::successors(Some(window.end), |&i| {
+                (i < len).then(|| (i + hop_size).min(len))
+            })
+            .map(|i| i.saturating_sub(window_size)..i);
+            if let Some(extended) = extend(source, detector, later, release_rms, false)? {
+                (cut, window) = extended;
+            }
+        }
+        let level = release_rms.unwrap_or(threshold_rms);
+        coarse.end = cut;
+        refined.end = refine(source, detector, window, refine_size, level, true)?.unwrap_or(cut);
+    }
+    if refined.start >= refined.end {
+        refined = coarse.clone();
+    }
+    Ok(Cuts {
+        coarse,
+        refined,
+        capped_start: false,
+        capped_end: false,
+    })
+}
+
+/// Slides a window from the first range of `windows` (a window already known to be loud)
+/// through the rest while it stays above `release_rms`, and returns the cut point and range of
+/// the last window that did, if it moved at all. `toward_start` tells whether the windows move
+/// toward the start of the source, and so which edge of each window's new frames is the cut.
+fn extend<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    mut windows: impl Iterator<Item = Range<usize>>,
+    release_rms: f64,
+    toward_start: bool,
+) -> Result<Option<(usize, Range<usize>)>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let mut window = Window::default();
+    if let Some(first) = windows.next() {
+        window.move_to(source, detector, first)?;
+    }
+    let mut last = None;
+    for range in windows {
+        let (level, added) = window.move_to(source, detector, range)?;
+        if level <= release_rms {
+            break;
+        }
+        let cut = if toward_start { added.start } else { added.end };
+        last = Some((cut, window.range.clone()));
+    }
+    Ok(last)
+}
+
+/// Slides an envelope of `size` frames one frame at a time across `region`, front to back (or
+/// back to front if `backward`), and returns the frame that first brought it above
+/// `threshold_rms`: the first loud frame going forward, or one past the last going backward.
+fn refine<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    region: Range<usize>,
+    size: usize,
+    threshold_rms: f64,
+    backward: bool,
+) -> Result<Option<usize>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let size = size.min(region.len()).max(1);
+    let mut window = Window::default();
+    let last = region.end.saturating_sub(size).max(region.start);
+    for step in 0..=last - region.start {
+        let start = if backward {
+            last - step
+        } else {
+            region.start + step
+        };
+        let (level, added) = window.move_to(source, detector, start..start + size)?;
+        if level > threshold_rms {
+            return Ok(Some(if backward { added.end } else { added.start }));
+        }
+    }
+    Ok(None)
+}
+
+/// Finds the silent regions inside `range` that are longer than `min_pause` frames. A region
+/// is a run of consecutive `window_size`-frame windows, `hop_size` frames apart, whose level
+/// as measured by `detector` stays at or below `threshold_db`; it spans from the start of its
+/// first window to the end of its last, so every frame in it was measured as silence. Regions
+/// touching either end of `range` are edges rather than pauses and are not returned.
+///
+/// # Panics
+///
+/// Panics if `hop_size` is zero.
+pub fn find_pauses<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    range: Range<usize>,
+    threshold_db: f64,
+    window_size: usize,
+    hop_size: usize,
+    min_pause: usize,
+) -> Result<Vec<Range<usize>>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    assert!(hop_size > 0, "hop size must be at least one frame");
+    let mut pauses = Vec::new();
+    if range.len() < window_size {
+        return Ok(pauses);
+    }
+    let threshold_rms = 10f64.powf(threshold_db / 20.0);
+    // The last window is aligned to the end of the range so that trailing silence always
+    // reaches it and is recognised as an edge.
+    let last = range.end - window_size;
+    let starts = (range.start..last)
+        .step_by(hop_size)
+        .chain(iter::once(last));
+
+    let mut quiet: Option<Range<usize>> = None;
+    for start in starts {
+        let window = start..start + window_size;
+        if detector.level(source, window.clone())? <= threshold_rms {
+            let region = quiet.get_or_insert(window.clone());
+            region.end = window.end;
+        } else if let Some(region) = quiet.take()
+            && region.start > range.start
+            && region.len() > min_pause
+        {
+            pauses.push(region);
+        }
+    }
+    Ok(pauses)
+}
+
+/// Returns the frame boundary in `candidates`, tried every `hop_size` frames from its start,
+/// around which a `window_size`-frame window has the lowest level as measured by `detector`:
+/// the quietest place to cut. At equal level the latest candidate wins, so cuts through evenly
+/// loud audio keep the piece before them as long as allowed.
+///
+/// # Panics
+///
+/// Panics if `candidates` is empty or `hop_size` is zero.
+pub fn quietest_frame<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    candidates: Range<usize>,
+    window_size: usize,
+    hop_size: usize,
+) -> Result<usize>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    assert!(!candidates.is_empty(), "no candidate cut points");
+    let len = source.frames();
+    let mut quietest = (f64::INFINITY, candidates.start);
+    for cut in candidates.step_by(hop_size) {
+        let start = cut.saturating_sub(window_size / 2);
+        let level = detector.level(source, start..(start + window_size).min(len))?;
+        if level <= quietest.0 {
+            quietest = (level, cut);
+        }
+    }
+    Ok(quietest.1)
+}
+
+/// Which end of the kept audio a cut point bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// The cut is the first kept frame.
+    Start,
+    /// The cut is one past the last kept frame.
+    End,
+}
+
+/// Moves the cut at frame boundary `cut` to the nearest point within `radius` frames where the
+/// waveform crosses zero, so the kept audio begins or ends without a step. Multichannel audio
+/// rarely crosses zero in every channel at once, so it (and mono audio with no crossing in
+/// range) snaps to the boundary whose edge frame has the lowest peak amplitude instead. At equal
+/// distance, the boundary that keeps more audio wins.
+pub fn snap_to_zero_crossing<S: FrameSource + ?Sized>(
+    source: &mut S,
+    cut: usize,
+    radius: usize,
+    edge: Edge,
+) -> Result<usize> {
+    let len = source.frames();
+    let channels = source.channels();
+    let lo = cut.saturating_sub(radius);
+    let hi = (cut + radius).min(len);
+    // One extra frame on each side so every candidate boundary has both neighbours loaded.
+    let read = lo.saturating_sub(1)..(hi + 1).min(len);
+    let mut samples = Vec::new();
+    source.read_samples(read.clone(), &mut samples)?;
+    let frame = |f: usize| &samples[(f - read.start) * channels..][..channels];
+
+    let mut candidates: Vec<usize> = (lo..=hi).collect();
+    candidates.sort_by_key(|&b| {
+        let keeps_more = match edge {
+            Edge::Start => b,
+            Edge::End => usize::MAX - b,
+        };
+        (b.abs_diff(cut), keeps_more)
+    });
+
+    if channels == 1 {
+        let crossing = candidates.iter().copied().find(|&b| {
+            if b == 0 || b == len {
+                return true;
+            }
+            let (before, after) = (frame(b - 1)[0], frame(b)[0]);
+            (before < 0.0) != (after < 0.0) || before == 0.0 || after == 0.0
+        });
+        if let Some(b) = crossing {
+            return Ok(b);
+        }
+    }
+
+    let peak = |b: usize| {
+        let edge_frame = match edge {
+            Edge::Start if b < len => b,
+            Edge::End if b > 0 => b - 1,
+            _ => return f64::INFINITY,
+        };
+        frame(edge_frame)
+            .iter()
+            .fold(0.0, |peak: f64, s| peak.max(s.abs()))
+    };
+    Ok(candidates
+        .into_iter()
+        .min_by(|&a, &b| peak(a).total_cmp(&peak(b)))
+        .unwrap_or(cut))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::Rms;
+    use crate::source::SliceSource;
+    use crate::source::fixtures::{MONO, STEREO, bursts, tone};
+    use hound::WavSpec;
+
+    /// Returns the RMS level of all of `samples`, measured afresh by the [`Rms`] detector.
+    fn rms_level(samples: &[i16], spec: WavSpec, policy: ChannelPolicy) -> f64 {
+        let mut source = SliceSource::new(samples, spec, policy);
+        let frames = source.frames();
+        Rms::default().level(&mut source, 0..frames).unwrap()
+    }
+
+    fn trim_mono(samples: &[i16], threshold_db: f64, window_size: usize) -> Range<usize> {
+        let mut source = SliceSource::new(samples, MONO, ChannelPolicy::Max);
+        trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(threshold_db, window_size, window_size),
+        )
+        .unwrap()
+        .refined
+    }
+
+    #[test]
+    fn test_trim_all_silence() {
+        let samples = vec![0i16; 1000];
+        let threshold_db = -50.0;
+        let window_size = 100;
+        let trimmed = trim_mono(&samples, threshold_db, window_size);
+        assert_eq!(trimmed.len(), 0);
+    }
+
+    #[test]
+    fn test_trim_leading_trailing_silence() {
+        let silence_len = 800;
+        let signal = vec![1000i16; 400]; // Above threshold RMS.
+        let samples = vec![0i16; silence_len]
+            .into_iter()
+            .chain(signal.clone())
+            .chain(vec![0i16; silence_len])
+            .collect::<Vec<_>>();
+        let threshold_db = -40.0; // Threshold such that RMS(1000 over 400) > threshold.
+        let window_size = 200;
+        let trimmed = trim_mono(&samples, threshold_db, window_size);
+        assert_eq!(trimmed.len(), signal.len());
+        assert_eq!(&samples[trimmed], &signal[..]);
+    }
+
+    #[test]
+    fn test_trim_no_silence() {
+        let samples = vec![1000i16; 1000];
+        let threshold_db = -60.0;
+        let window_size = 100;
+        let trimmed = trim_mono(&samples, threshold_db, window_size);
+        assert_eq!(trimmed.len(), samples.len());
+    }
+
+    #[test]
+    fn test_rms_channel_policies() {
+        // Two stereo frames: left is loud, right is silent.
+        let chunk = vec![1000i16, 0, 1000, 0];
+        let level = 1000.0 / 32768.0;
+        let max = rms_level(&chunk, STEREO, ChannelPolicy::Max);
+        let mean = rms_level(&chunk, STEREO, ChannelPolicy::Mean);
+        let right = rms_level(&chunk, STEREO, ChannelPolicy::Channel(1));
+        assert!((max - level).abs() < 1e-9);
+        assert!((mean - level / 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(right, 0.0);
+    }
+
+    #[test]
+    fn test_trim_stereo_keeps_channels_aligned() {
+        // Sound only on the right channel; frames are (left, right).
+        let mut samples = vec![0i16; 2 * 400];
+        samples.extend((0..200).flat_map(|i| [i as i16, 1000]));
+        samples.extend(vec![0i16; 2 * 400]);
+        let mut source = SliceSource::new(&samples, STEREO, ChannelPolicy::Max);
+        let trimmed = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(-40.0, 200, 200),
+        )
+        .unwrap()
+        .refined;
+        assert_eq!(trimmed, 400..600);
+        assert_eq!(&samples[trimmed.start * 2..][..4], &[0, 1000, 1, 1000]);
+
+        // Detecting on the silent left channel alone trims everything.
+        let mut source = SliceSource::new(&samples, STEREO, ChannelPolicy::Channel(0));
+        let trimmed = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(-40.0, 200, 200),
+        )
+        .unwrap()
+        .refined;
+        assert!(trimmed.is_empty());
+    }
+
+    #[test]
+    fn test_trim_sliding_window_finds_onset_between_blocks() {
+        // Sound starts and stops 150 frames into a 200-frame block, which non-overlapping
+        // blocks would round out to block boundaries (or miss if the burst were shorter).
+        let mut samples = vec![0i16; 950];
+        samples.extend(vec![1000i16; 500]);
+        samples.extend(vec![0i16; 950]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let blocks = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(-40.0, 200, 200),
+        )
+        .unwrap();
+        assert_eq!(blocks.coarse, 800..1600);
+
+        let sliding = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(-40.0, 200, 50),
+        )
+        .unwrap();
+        assert_eq!(sliding.coarse, 950..1450);
+    }
+
+    #[test]
+    fn test_min_sound_ignores_clicks() {
+        // A 5-frame click in the leading silence and another in the trailing silence.
+        let mut samples = vec![0i16; 2000];
+        samples[300..305].fill(20_000);
+        samples[800..1200].fill(1000);
+        samples[1700..1705].fill(20_000);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let params = ScanParams::new(-40.0, 100, 20);
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &params)
+                .unwrap()
+                .refined,
+            300..1705
+        );
+
+        // Every window overlapping a click is loud, but its loud frames only span 5 frames.
+        let params = params.with_min_sound(50, 1.0);
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &params)
+                .unwrap()
+                .refined,
+            800..1200
+        );
+
+        // Sound longer than the source never qualifies.
+        let params = params.with_min_sound(5000, 1.0);
+        assert!(
+            trim_samples(&mut source, &mut Rms::default(), &params)
+                .unwrap()
+                .refined
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_min_sound_counts_sound_just_above_threshold_by_its_length() {
+        // A 1 kHz tone at -23 dBFS RMS against a -25 dBFS threshold, in 50 ms windows: windows
+        // covering less than about two thirds of the tone stay below the threshold.
+        let params = ScanParams {
+            refine_size: 16,
+            ..ScanParams::new(-25.0, 800, 160)
+        }
+        .with_min_sound(1600, 1.0);
+        let trim = |frames: usize| {
+            let mut samples = vec![0i16; 4000];
+            samples.extend(tone(1000.0, frames));
+            samples.extend(vec![0i16; 4000]);
+            let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+            trim_samples(&mut source, &mut Rms::default(), &params)
+                .unwrap()
+                .refined
+        };
+        let kept = trim(1700);
+        assert!((4000..4016).contains(&kept.start), "{kept:?}");
+        assert!((5684..=5700).contains(&kept.end), "{kept:?}");
+        assert!(trim(1500).is_empty());
+    }
+
+    #[test]
+    fn test_min_sound_n_of_m_bridges_dropouts() {
+        // Sound with a short dropout: windows straddling the gap fall below the threshold.
+        let mut samples = vec![0i16; 2000];
+        samples[800..1000].fill(2000);
+        samples[1060..1200].fill(2000);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let strict = ScanParams::new(-30.0, 40, 20).with_min_sound(300, 1.0);
+        assert!(
+            trim_samples(&mut source, &mut Rms::default(), &strict)
+                .unwrap()
+                .refined
+                .is_empty()
+        );
+        let lenient = ScanParams::new(-30.0, 40, 20).with_min_sound(300, 0.75);
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &lenient)
+                .unwrap()
+                .refined,
+            800..1200
+        );
+    }
+
+    #[test]
+    fn test_hysteresis_extends_to_release_level() {
+        // A quiet onset at -40 dBFS, a loud body at -20 dBFS and a quiet tail.
+        let mut samples = vec![0i16; 1000];
+        samples.extend(vec![328i16; 300]);
+        samples.extend(vec![3277i16; 400]);
+        samples.extend(vec![328i16; 500]);
+        samples.extend(vec![0i16; 1000]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+
+        let trigger = ScanParams::new(-30.0, 100, 20);
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &trigger)
+                .unwrap()
+                .refined,
+            1300..1700
+        );
+
+        let hysteresis = ScanParams {
+            release_db: Some(-45.0),
+            ..trigger
+        };
+        let cuts = trim_samples(&mut source, &mut Rms::default(), &hysteresis).unwrap();
+        assert_eq!(cuts.coarse, 940..2260);
+        assert_eq!(cuts.refined, 1000..2200);
+
+        // A release level at the trigger level changes nothing.
+        let same = ScanParams {
+            release_db: Some(-30.0),
+            ..trigger
+        };
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &same)
+                .unwrap()
+                .refined,
+            1300..1700
+        );
+    }
+
+    #[test]
+    fn test_edges_use_own_thresholds_or_stay_put() {
+        // Clean start, then a noisy tail at about -40 dBFS after the speech.
+        let mut samples = vec![0i16; 1000];
+        samples.extend(vec![3277i16; 500]);
+        samples.extend((0..1000).map(|i| if i % 2 == 0 { 328 } else { -328 }));
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+
+        let both = ScanParams::new(-50.0, 100, 20);
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &both)
+                .unwrap()
+                .refined,
+            1000..2500
+        );
+        let noisy_end = ScanParams {
+            end_db: Some(-30.0),
+            ..both
+        };
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &noisy_end)
+                .unwrap()
+                .refined,
+            1000..1500
+        );
+
+        let only_start = ScanParams {
+            end_db: None,
+            ..noisy_end
+        };
+        let cuts = trim_samples(&mut source, &mut Rms::default(), &only_start).unwrap();
+        assert_eq!((cuts.coarse.end, cuts.refined), (2500, 1000..2500));
+        let only_end = ScanParams {
+            start_db: None,
+            ..noisy_end
+        };
+        let cuts = trim_samples(&mut source, &mut Rms::default(), &only_end).unwrap();
+        assert_eq!((cuts.coarse.start, cuts.refined), (0, 0..1500));
+
+        // An edge left untouched still yields nothing for an all-silent source.
+        let silence = vec![0i16; 1000];
+        let mut source = SliceSource::new(&silence, MONO, ChannelPolicy::Max);
+        assert!(
+            trim_samples(&mut source, &mut Rms::default(), &only_end)
+                .unwrap()
+                .refined
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_max_trim_caps_each_edge() {
+        let mut samples = vec![0i16; 1000];
+        samples.extend(vec![3277i16; 500]);
+        samples.extend(vec![0i16; 1000]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+
+        let params = ScanParams {
+            max_trim_start: Some(400),
+            max_trim_end: Some(1000),
+            ..ScanParams::new(-50.0, 100, 20)
+        };
+        let cuts = trim_samples(&mut source, &mut Rms::default(), &params).unwrap();
+        assert_eq!(cuts.refined, 400..1500);
+        assert_eq!(cuts.coarse.start, 400);
+        assert!(cuts.capped_start && !cuts.capped_end);
+
+        // Silence counts as leading, so only a start limit keeps anything.
+        let silence = vec![0i16; 1000];
+        let mut source = SliceSource::new(&silence, MONO, ChannelPolicy::Max);
+        let cuts = trim_samples(&mut source, &mut Rms::default(), &params).unwrap();
+        assert_eq!((cuts.refined, cuts.capped_start), (400..1000, true));
+        let end_only = ScanParams {
+            max_trim_start: None,
+            ..params
+        };
+        assert_eq!(
+            trim_samples(&mut source, &mut Rms::default(), &end_only).unwrap(),
+            Cuts::SILENT
+        );
+    }
+
+    #[test]
+    fn test_find_pauses_skips_edges_and_short_gaps() {
+        let mut samples = vec![0i16; 500];
+        samples.extend(bursts(&[(1000, 1000), (1000, 200), (1000, 500)]));
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+
+        let pauses = find_pauses(
+            &mut source,
+            &mut Rms::default(),
+            0..samples.len(),
+            -50.0,
+            100,
+            20,
+            300,
+        )
+        .unwrap();
+        assert_eq!(pauses, vec![1500..2500]);
+        // A range starting inside the first pause makes it an edge.
+        let pauses = find_pauses(
+            &mut source,
+            &mut Rms::default(),
+            2000..samples.len(),
+            -50.0,
+            100,
+            20,
+            100,
+        )
+        .unwrap();
+        assert_eq!(pauses, vec![3500..3700]);
+    }
+
+    #[test]
+    fn test_quietest_frame_finds_dip() {
+        // A steady tone with a 100-frame dip to a tenth of its level around frame 3000.
+        let samples: Vec<i16> = (0..6000)
+            .map(|i| {
+                let level = if (2950..3050).contains(&i) { 300 } else { 3000 };
+                if i % 2 == 0 { level } else { -level }
+            })
+            .collect();
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        assert_eq!(
+            quietest_frame(&mut source, &mut Rms::default(), 1000..5000, 100, 50).unwrap(),
+            3000
+        );
+        assert_eq!(
+            quietest_frame(&mut source, &mut Rms::default(), 1000..2000, 100, 50).unwrap(),
+            1950
+        );
+    }
+
+    #[test]
+    fn test_refinement_finds_exact_boundaries() {
+        // A soft onset and release sit inside the windows that trigger detection.
+        let mut samples = vec![0i16; 1003];
+        samples.extend((0..100).map(|i| i * 10));
+        samples.extend(vec![1000i16; 400]);
+        samples.extend((0..100).map(|i| 1000 - i * 10));
+        samples.extend(vec![0i16; 997]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let cuts = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams::new(-40.0, 200, 50),
+        )
+        .unwrap();
+        assert_eq!(cuts.coarse, 1050..1550);
+
+        // -40 dBFS is a sample value of about 328.
+        assert_eq!(cuts.refined, 1036..1571);
+        assert!(samples[cuts.refined.start] > 327 && samples[cuts.refined.start - 1] <= 327);
+        assert!(samples[cuts.refined.end - 1] > 327 && samples[cuts.refined.end] <= 327);
+
+        // A longer envelope smooths over single samples but stays within the triggering windows.
+        let smoothed = trim_samples(
+            &mut source,
+            &mut Rms::default(),
+            &ScanParams {
+                refine_size: 16,
+                ..ScanParams::new(-40.0, 200, 50)
+            },
+        )
+        .unwrap();
+        assert!(smoothed.refined.start > 1003 && smoothed.refined.start < 1050);
+        assert!(smoothed.refined.end > 1550 && smoothed.refined.end < 1603);
+    }
+
+    #[test]
+    fn test_snap_to_zero_crossing_mono() {
+        // Crossings between frames 9/10 (+ to -) and 19/20 (- to +).
+        let samples: Vec<i16> = (0..30)
+            .map(|i| if (10..20).contains(&i) { -1000 } else { 1000 })
+            .collect();
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let snap = |source: &mut SliceSource<i16>, cut, radius, edge| {
+            snap_to_zero_crossing(source, cut, radius, edge).unwrap()
+        };
+        assert_eq!(snap(&mut source, 13, 5, Edge::Start), 10);
+        assert_eq!(snap(&mut source, 17, 5, Edge::End), 20);
+        // Equidistant crossings: keep more audio.
+        assert_eq!(snap(&mut source, 15, 5, Edge::Start), 10);
+        assert_eq!(snap(&mut source, 15, 5, Edge::End), 20);
+        // No crossing in reach leaves a flat signal where it was.
+        assert_eq!(snap(&mut source, 5, 2, Edge::Start), 5);
+    }
+
+    #[test]
+    fn test_snap_multichannel_picks_quietest_edge_frame() {
+        let mut samples = vec![5000i16; 2 * 40];
+        samples[2 * 23] = 30; // Frame 23: quiet left, loud right.
+        samples[2 * 26] = 40;
+        samples[2 * 26 + 1] = -20; // Frame 26: quiet in both channels.
+        let mut source = SliceSource::new(&samples, STEREO, ChannelPolicy::Max);
+        assert_eq!(
+            snap_to_zero_crossing(&mut source, 24, 4, Edge::Start).unwrap(),
+            26
+        );
+        // The end edge is the frame before the boundary.
+        assert_eq!(
+            snap_to_zero_crossing(&mut source, 24, 4, Edge::End).unwrap(),
+            27
+        );
+    }
+
+    #[test]
+    fn test_sliding_window_sum_matches_direct_rms() {
+        let samples: Vec<i16> = (0..5000)
+            .map(|i| ((i * 7919) % 20000 - 10000) as i16)
+            .collect();
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let mut detector = Rms::default();
+        let mut window = Window::default();
+        for start in (0..4600).step_by(30) {
+            let (level, added) = window
+                .move_to(&mut source, &mut detector, start..start + 400)
+                .unwrap();
+            let expected_added = if start == 0 {
+                0..400
+            } else {
+                start + 370..start + 400
+            };
+            assert_eq!(added, expected_added);
+            let direct = rms_level(&samples[start..start + 400], MONO, ChannelPolicy::Max);
+            assert!((level - direct).abs() < 1e-12);
+        }
+        for end in (400..=5000).rev().step_by(70) {
+            let (level, _) = window
+                .move_to(&mut source, &mut detector, end - 400..end)
+                .unwrap();
+            let direct = rms_level(&samples[end - 400..end], MONO, ChannelPolicy::Max);
+            assert!((level - direct).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_noise_floor_percentile() {
+        // 8 quiet windows at -60 dBFS (rms 32.77) and 2 loud ones.
+        let mut samples = Vec::new();
+        for i in 0..10 {
+            let level = if i % 5 == 4 { 16_384 } else { 33 };
+            samples.extend((0..100).map(|j| if j % 2 == 0 { level } else { -level }));
+        }
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let floor = noise_floor_db(&mut source, &mut Rms::default(), 100, 10.0)
+            .unwrap()
+            .unwrap();
+        assert!((floor - 20.0 * (33.0f64 / 32768.0).log10()).abs() < 0.1);
+        let loud = noise_floor_db(&mut source, &mut Rms::default(), 100, 100.0)
+            .unwrap()
+            .unwrap();
+        assert!((loud - 20.0 * 0.5f64.log10()).abs() < 0.1);
+
+        // Digital silence sits at the bottom of the histogram.
+        let silence = vec![0i16; 1000];
+        let mut source = SliceSource::new(&silence, MONO, ChannelPolicy::Max);
+        let floor = noise_floor_db(&mut source, &mut Rms::default(), 100, 10.0)
+            .unwrap()
+            .unwrap();
+        assert!(floor < FLOOR_MIN_DB + FLOOR_BIN_DB);
+
+        let mut empty = SliceSource::new(&[] as &[i16], MONO, ChannelPolicy::Max);
+        assert_eq!(
+            noise_floor_db(&mut empty, &mut Rms::default(), 100, 10.0).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reference_level() {
+        // Half-scale click in a window of quarter-scale signal.
+        let mut samples = vec![8192i16; 400];
+        samples[250] = -16_384;
+        samples.extend(vec![0i16; 400]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let peak = reference_level_db(
+            &mut source,
+            &mut Rms::default(),
+            100,
+            ThresholdReference::Peak,
+        )
+        .unwrap();
+        assert!((peak - 20.0 * 0.5f64.log10()).abs() < 1e-9);
+        let window = reference_level_db(
+            &mut source,
+            &mut Rms::default(),
+            100,
+            ThresholdReference::LoudestWindow,
+        )
+        .unwrap();
+        let expected = 10.0 * ((99.0 * 0.0625 + 0.25) / 100.0f64).log10();
+        assert!((window - expected).abs() < 1e-9);
+
+        let silence = vec![0i16; 100];
+        let mut source = SliceSource::new(&silence, MONO, ChannelPolicy::Max);
+        let level = reference_level_db(
+            &mut source,
+            &mut Rms::default(),
+            100,
+            ThresholdReference::Peak,
+        )
+        .unwrap();
+        assert_eq!(level, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_adaptive_threshold_clamps() {
+        let adaptive = AdaptiveThreshold {
+            percentile: 10.0,
+            margin_db: 12.0,
+            min_db: Some(-70.0),
+            max_db: Some(-30.0),
+        };
+        assert_eq!(adaptive.threshold_db(-60.0), -48.0);
+        assert_eq!(adaptive.threshold_db(-100.0), -70.0);
+        assert_eq!(adaptive.threshold_db(-20.0), -30.0);
+    }
+
+    #[test]
+    fn test_ms_to_frames() {
+        assert_eq!(ms_to_frames(50.0, 16_000), 800);
+        assert_eq!(ms_to_frames(50.0, 8_000), 400);
+        assert_eq!(ms_to_frames(50.0, 22_050), 1103);
+        assert_eq!(ms_to_frames(50.0, 44_100), 2205);
+        assert_eq!(ms_to_frames(50.0, 48_000), 2400);
+        assert_eq!(ms_to_frames(0.001, 8_000), 1);
+        assert_eq!(ms_to_frame_count(0.0, 48_000), 0);
+        assert_eq!(ms_to_frame_count(20.0, 44_100), 882);
+    }
+
+    #[test]
+    fn test_channel_policy_from_str() {
+        assert_eq!("max".parse(), Ok(ChannelPolicy::Max));
+        assert_eq!("mean".parse(), Ok(ChannelPolicy::Mean));
+        assert_eq!("3".parse(), Ok(ChannelPolicy::Channel(3)));
+        assert!("loudest".parse::<ChannelPolicy>().is_err());
+    }
+}
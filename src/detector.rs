@@ -0,0 +1,435 @@
+//! The built-in [`Detector`]s, which decide what counts as sound in a detection window.
+
+use crate::detect::{ChannelPolicy, Detector, FrameSource, ms_to_frames};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::VecDeque;
+use std::f64::consts::{PI, SQRT_2};
+use std::ops::Range;
+
+/// Half the span, in milliseconds, over which [`EnergyZcr`] counts zero crossings for a frame.
+const ZCR_HALF_SPAN_MS: f64 = 5.0;
+/// How much a window crossing zero at every frame is boosted by [`EnergyZcr`], as a power ratio
+/// above 1.0 (10 dB at most).
+const ZCR_WEIGHT: f64 = 9.0;
+/// Edges of the band that [`Spectral`] measures, in Hz.
+const SPEECH_BAND_HZ: (f64, f64) = (300.0, 3400.0);
+/// Audio run through the [`Spectral`] filters before the first frame read, in milliseconds, so
+/// their start-up transient has died away.
+const FILTER_WARMUP_MS: f64 = 20.0;
+
+/// Frame powers covered by the window last measured, with a running sum so that sliding the
+/// window by one hop costs time proportional to the hop rather than the window.
+#[derive(Default)]
+struct SlidingWindow {
+    range: Range<usize>,
+    powers: VecDeque<f64>,
+    sum: f64,
+    /// Frames subtracted from `sum` since it was last recomputed from scratch.
+    removed: usize,
+    buf: Vec<f64>,
+}
+
+impl SlidingWindow {
+    /// Moves the window to cover `range`, calling `read` to replace the contents of a buffer
+    /// with the powers of the frames in a range only for the frames not already held.
+    fn move_to(
+        &mut self,
+        range: Range<usize>,
+        mut read: impl FnMut(Range<usize>, &mut Vec<f64>) -> Result<()>,
+    ) -> Result<()> {
+        if range.start >= self.range.end || range.end <= self.range.start {
+            self.powers.clear();
+            self.sum = 0.0;
+            self.removed = 0;
+            self.range = range.start..range.start;
+        }
+
+        let front = range.start.saturating_sub(self.range.start);
+        let back = self.range.end.saturating_sub(range.end);
+        for p in self.powers.drain(..front) {
+            self.sum -= p;
+        }
+        for p in self.powers.drain(self.powers.len() - back..) {
+            self.sum -= p;
+        }
+        self.removed += front + back;
+        self.range = self.range.start + front..self.range.end - back;
+
+        if range.start < self.range.start {
+            read(range.start..self.range.start, &mut self.buf)?;
+            self.sum += self.buf.iter().sum::<f64>();
+            for &p in self.buf.iter().rev() {
+                self.powers.push_front(p);
+            }
+        }
+        if range.end > self.range.end {
+            read(self.range.end..range.end, &mut self.buf)?;
+            self.sum += self.buf.iter().sum::<f64>();
+            self.powers.extend(&self.buf);
+        }
+        self.range = range;
+
+        // Repeated subtraction accumulates rounding error; once every held frame has been
+        // replaced, recomputing the sum costs no more than the reads that got us here.
+        if self.removed >= self.powers.len() {
+            self.sum = self.powers.iter().sum();
+            self.removed = 0;
+        }
+        Ok(())
+    }
+
+    /// Returns the mean power of the frames in the window.
+    fn mean(&self) -> f64 {
+        if self.powers.is_empty() {
+            return 0.0;
+        }
+        self.sum.max(0.0) / self.powers.len() as f64
+    }
+
+    /// Returns the largest power of the frames in the window.
+    fn max(&self) -> f64 {
+        self.powers.iter().copied().fold(0.0, f64::max)
+    }
+}
+
+/// The built-in detectors, as selected on the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DetectorKind {
+    /// RMS level of the window.
+    #[default]
+    Rms,
+    /// Level of the loudest frame in the window, so short transients count as sound.
+    Peak,
+    /// RMS level boosted by up to 10 dB where the signal crosses zero often, keeping soft
+    /// fricatives and breaths at the edges of speech.
+    EnergyZcr,
+    /// RMS level of the speech band (300-3400 Hz) only, ignoring rumble, hum and hiss outside
+    /// it.
+    Spectral,
+}
+
+impl DetectorKind {
+    /// Creates this detector for a file at `sample_rate` whose channels are combined per
+    /// `policy`.
+    pub fn build<S: FrameSource + ?Sized>(
+        self,
+        sample_rate: u32,
+        policy: ChannelPolicy,
+    ) -> Box<dyn Detector<S>> {
+        match self {
+            DetectorKind::Rms => Box::new(Rms::default()),
+            DetectorKind::Peak => Box::new(Peak::default()),
+            DetectorKind::EnergyZcr => Box::new(EnergyZcr::new(sample_rate, policy)),
+            DetectorKind::Spectral => Box::new(Spectral::new(sample_rate, policy)),
+        }
+    }
+}
+
+/// Measures each window by its RMS level: the root of the mean of the source's frame powers.
+#[derive(Default)]
+pub struct Rms {
+    window: SlidingWindow,
+}
+
+impl<S: FrameSource + ?Sized> Detector<S> for Rms {
+    fn level(&mut self, source: &mut S, range: Range<usize>) -> Result<f64> {
+        self.window
+            .move_to(range, |range, out| source.read_powers(range, out))?;
+        Ok(self.window.mean().sqrt())
+    }
+}
+
+/// Measures each window by its loudest frame, so that short transients count as sound.
+#[derive(Default)]
+pub struct Peak {
+    window: SlidingWindow,
+}
+
+impl<S: FrameSource + ?Sized> Detector<S> for Peak {
+    fn level(&mut self, source: &mut S, range: Range<usize>) -> Result<f64> {
+        self.window
+            .move_to(range, |range, out| source.read_powers(range, out))?;
+        Ok(self.window.max().sqrt())
+    }
+}
+
+/// Returns the signal whose zero crossings are counted for each interleaved frame of
+/// `samples`: the selected channel, or the mean of all of them.
+fn mixdown(samples: &[f64], channels: usize, policy: ChannelPolicy) -> impl Iterator<Item = f64> {
+    samples
+        .chunks_exact(channels)
+        .map(move |frame| match policy {
+            ChannelPolicy::Channel(ch) => frame[ch],
+            _ => frame.iter().sum::<f64>() / channels as f64,
+        })
+}
+
+/// Measures each window by the RMS level of its frame powers scaled up by the rate at which the
+/// signal crosses zero around each frame, so that noisy, high-frequency sounds such as
+/// fricatives read louder than their energy alone, while steady low-frequency sound reads about
+/// the same as with [`Rms`].
+pub struct EnergyZcr {
+    window: SlidingWindow,
+    weighting: ZcrWeighting,
+}
+
+impl EnergyZcr {
+    /// Creates a detector for a file at `sample_rate` whose channels are combined per
+    /// `policy`.
+    pub fn new(sample_rate: u32, policy: ChannelPolicy) -> Self {
+        Self {
+            window: SlidingWindow::default(),
+            weighting: ZcrWeighting {
+                policy,
+                half_span: ms_to_frames(ZCR_HALF_SPAN_MS, sample_rate),
+                samples: Vec::new(),
+                crossings: Vec::new(),
+            },
+        }
+    }
+}
+
+impl<S: FrameSource + ?Sized> Detector<S> for EnergyZcr {
+    fn level(&mut self, source: &mut S, range: Range<usize>) -> Result<f64> {
+        self.window.move_to(range, |range, out| {
+            self.weighting.read_powers(source, range, out)
+        })?;
+        Ok(self.window.mean().sqrt())
+    }
+}
+
+/// The zero-crossing weighting of [`EnergyZcr`].
+struct ZcrWeighting {
+    policy: ChannelPolicy,
+    /// Frames on either side of a frame whose zero crossings count toward its rate.
+    half_span: usize,
+    samples: Vec<f64>,
+    /// Running count of crossings, one entry per frame read plus one.
+    crossings: Vec<usize>,
+}
+
+impl ZcrWeighting {
+    /// Replaces the contents of `out` with the weighted power of each frame in `range` of
+    /// `source`.
+    fn read_powers<S: FrameSource + ?Sized>(
+        &mut self,
+        source: &mut S,
+        range: Range<usize>,
+        out: &mut Vec<f64>,
+    ) -> Result<()> {
+        // One extra frame before the span tells whether its first frame crossed zero.
+        let start = range.start.saturating_sub(self.half_span + 1);
+        let end = (range.end + self.half_span).min(source.frames());
+        source.read_samples(start..end, &mut self.samples)?;
+        self.crossings.clear();
+        self.crossings.push(0);
+        let mut previous: Option<f64> = None;
+        for value in mixdown(&self.samples, source.channels(), self.policy) {
+            let crossed = previous.is_some_and(|p| (p < 0.0) != (value < 0.0));
+            self.crossings
+                .push(self.crossings[self.crossings.len() - 1] + crossed as usize);
+            previous = Some(value);
+        }
+
+        source.read_powers(range.clone(), out)?;
+        for (frame, power) in range.zip(out.iter_mut()) {
+            let lo = frame.saturating_sub(self.half_span).max(start + 1);
+            let hi = (frame + self.half_span + 1).min(end);
+            if hi > lo {
+                let count = self.crossings[hi - start] - self.crossings[lo - start];
+                *power *= 1.0 + ZCR_WEIGHT * count as f64 / (hi - lo) as f64;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Second-order IIR filter section with unity gain in its passband (transposed direct form
+/// II).
+#[derive(Clone, Copy, Debug)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    /// Butterworth high-pass (`high`) or low-pass section with its corner at `cutoff` Hz.
+    fn butterworth(cutoff: f64, sample_rate: u32, high: bool) -> Self {
+        let w0 = 2.0 * PI * cutoff / sample_rate as f64;
+        let alpha = w0.sin() / SQRT_2;
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        let b = if high {
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0]
+        } else {
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0]
+        };
+        Self {
+            b: b.map(|b| b / a0),
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            state: [0.0; 2],
+        }
+    }
+
+    /// Filters one sample.
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// Measures each window by the RMS level of the speech band only, filtering every channel
+/// through a high-pass and a low-pass section before combining them per the channel policy. A
+/// tone inside the band measures the same as with [`Rms`].
+pub struct Spectral {
+    window: SlidingWindow,
+    band: BandPass,
+}
+
+impl Spectral {
+    /// Creates a detector for a file at `sample_rate` whose channels are combined per
+    /// `policy`. The upper band edge is lowered to stay below the Nyquist frequency.
+    pub fn new(sample_rate: u32, policy: ChannelPolicy) -> Self {
+        let (low, high) = SPEECH_BAND_HZ;
+        let high = high.min(0.45 * sample_rate as f64);
+        Self {
+            window: SlidingWindow::default(),
+            band: BandPass {
+                policy,
+                filter: [
+                    Biquad::butterworth(low, sample_rate, true),
+                    Biquad::butterworth(high, sample_rate, false),
+                ],
+                warmup: ms_to_frames(FILTER_WARMUP_MS, sample_rate),
+                samples: Vec::new(),
+            },
+        }
+    }
+}
+
+impl<S: FrameSource + ?Sized> Detector<S> for Spectral {
+    fn level(&mut self, source: &mut S, range: Range<usize>) -> Result<f64> {
+        self.window.move_to(range, |range, out| {
+            self.band.read_powers(source, range, out)
+        })?;
+        Ok(self.window.mean().sqrt())
+    }
+}
+
+/// The speech-band filtering of [`Spectral`].
+struct BandPass {
+    policy: ChannelPolicy,
+    /// Band-pass filter in its initial state: high-pass then low-pass.
+    filter: [Biquad; 2],
+    warmup: usize,
+    samples: Vec<f64>,
+}
+
+impl BandPass {
+    /// Replaces the contents of `out` with the in-band power of each frame in `range` of
+    /// `source`.
+    fn read_powers<S: FrameSource + ?Sized>(
+        &mut self,
+        source: &mut S,
+        range: Range<usize>,
+        out: &mut Vec<f64>,
+    ) -> Result<()> {
+        // Each read starts the filters afresh a little earlier, so the powers of a frame do not
+        // depend on which frames were read before it.
+        let start = range.start.saturating_sub(self.warmup);
+        source.read_samples(start..range.end, &mut self.samples)?;
+        let channels = source.channels();
+        let mut filters = vec![self.filter; channels];
+        let mut powers = vec![0.0; channels];
+        out.clear();
+        for (i, frame) in self.samples.chunks_exact(channels).enumerate() {
+            for ((power, filter), &x) in powers.iter_mut().zip(&mut filters).zip(frame) {
+                let [high_pass, low_pass] = filter;
+                *power = low_pass.process(high_pass.process(x)).powi(2);
+            }
+            if start + i >= range.start {
+                out.push(self.policy.combine(&powers));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::{ScanParams, trim_samples};
+    use crate::source::SliceSource;
+    use crate::source::fixtures::{MONO, tone};
+
+    /// Mean detection power of `samples` under `kind`, skipping the first 20 ms.
+    fn mean_power(samples: &[i16], kind: DetectorKind) -> f64 {
+        let mut source = SliceSource::new(samples, MONO, ChannelPolicy::Max);
+        let mut detector = kind.build(16_000, ChannelPolicy::Max);
+        let level = detector.level(&mut source, 320..samples.len()).unwrap();
+        level * level
+    }
+
+    #[test]
+    fn test_rms_silence() {
+        let mut source = SliceSource::new(&[0i16; 10], MONO, ChannelPolicy::Max);
+        assert_eq!(Rms::default().level(&mut source, 0..10).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_rms_full_scale() {
+        let mut source = SliceSource::new(&[32767i16; 10], MONO, ChannelPolicy::Max);
+        let level = Rms::default().level(&mut source, 0..10).unwrap();
+        assert!((level - 32767.0 / 32768.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_peak_detector_keeps_clicks() {
+        // A lone click at half scale in 1600 frames of silence: its 100-frame windows have an
+        // RMS level of 0.05 but a peak of 0.5.
+        let mut samples = vec![0i16; 1600];
+        samples[800] = 16_384;
+        let params = ScanParams::new(-20.0, 100, 10);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let rms = trim_samples(&mut source, &mut Rms::default(), &params).unwrap();
+        assert!(rms.refined.is_empty());
+        let peak = trim_samples(&mut source, &mut Peak::default(), &params).unwrap();
+        assert_eq!(peak.refined, 800..801);
+    }
+
+    #[test]
+    fn test_energy_zcr_lifts_high_frequency_sound() {
+        let low = tone(100.0, 3200);
+        let high = tone(7000.0, 3200);
+        let rms = mean_power(&high, DetectorKind::Rms);
+        // 100 Hz crosses zero about once every 80 frames; 7 kHz nearly every frame.
+        let low_ratio = mean_power(&low, DetectorKind::EnergyZcr) / rms;
+        let high_ratio = mean_power(&high, DetectorKind::EnergyZcr) / rms;
+        assert!((1.0..1.2).contains(&low_ratio), "{low_ratio}");
+        assert!((8.0..10.0).contains(&high_ratio), "{high_ratio}");
+    }
+
+    #[test]
+    fn test_spectral_ignores_sound_outside_speech_band() {
+        let voice = tone(1000.0, 3200);
+        let hum = tone(50.0, 3200);
+        let rms = mean_power(&voice, DetectorKind::Rms);
+        let voice_ratio = mean_power(&voice, DetectorKind::Spectral) / rms;
+        assert!((0.9..1.1).contains(&voice_ratio), "{voice_ratio}");
+        // Two octaves and more below the band, hum loses well over 20 dB.
+        assert!(mean_power(&hum, DetectorKind::Spectral) < rms / 100.0);
+
+        // A window measures the same whether or not the detector has just read around it,
+        // once the filters have warmed up.
+        let mut source = SliceSource::new(&voice, MONO, ChannelPolicy::Max);
+        let mut fresh = Spectral::new(16_000, ChannelPolicy::Max);
+        let part = fresh.level(&mut source, 2000..2100).unwrap();
+        let mut slid = Spectral::new(16_000, ChannelPolicy::Max);
+        slid.level(&mut source, 0..3200).unwrap();
+        assert!((slid.level(&mut source, 2000..2100).unwrap() - part).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,232 @@
+//! Fade-in and fade-out applied to the kept audio as it is copied to the output.
+
+use crate::source::{CHUNK_FRAMES, PcmSample, SampleTypeVisitor, with_sample_type};
+use anyhow::Result;
+use clap::ValueEnum;
+use hound::WavSpec;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::io::{self, Read};
+
+/// Gain curve of a fade, from silence to full level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FadeCurve {
+    /// Gain rises in a straight line.
+    #[default]
+    Linear,
+    /// Quarter sine, keeping perceived loudness roughly constant across the fade.
+    EqualPower,
+    /// Half cosine (Hann) shape, smooth at both ends of the fade.
+    RaisedCosine,
+}
+
+impl FadeCurve {
+    /// Returns the gain at position `t` of a fade-in, where `t` runs from 0.0 to 1.0.
+    pub fn gain(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+            FadeCurve::RaisedCosine => 0.5 * (1.0 - (t * PI).cos()),
+        }
+    }
+}
+
+/// Fade lengths in frames at each end of the kept audio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fades {
+    /// Frames over which the start fades in.
+    pub fade_in: u64,
+    /// Frames over which the end fades out.
+    pub fade_out: u64,
+    /// Shape of both fades.
+    pub curve: FadeCurve,
+}
+
+impl Fades {
+    /// Returns `true` if neither end is faded.
+    pub fn is_none(&self) -> bool {
+        self.fade_in == 0 && self.fade_out == 0
+    }
+
+    /// Returns the gain for `frame` of a kept range `frames` long. The first frame of a fade-in
+    /// and the last frame of a fade-out are silent; fades longer than the range overlap and
+    /// their gains multiply.
+    pub fn gain(&self, frame: u64, frames: u64) -> f64 {
+        let mut gain = 1.0;
+        if frame < self.fade_in {
+            gain *= self.curve.gain(frame as f64 / self.fade_in as f64);
+        }
+        let from_end = frames - 1 - frame;
+        if from_end < self.fade_out {
+            gain *= self.curve.gain(from_end as f64 / self.fade_out as f64);
+        }
+        gain
+    }
+}
+
+/// Scales every sample of one interleaved frame, decoded as `T`, by `gain`.
+fn scale_frame<T: PcmSample>(frame: &mut [u8], sample_len: usize, gain: f64) {
+    for sample in frame.chunks_exact_mut(sample_len) {
+        T::decode(sample).scaled(gain).encode(sample);
+    }
+}
+
+/// Picks [`scale_frame`] for the sample type of a format.
+struct ScaleFrame;
+
+impl SampleTypeVisitor for ScaleFrame {
+    type Output = fn(&mut [u8], usize, f64);
+
+    fn visit<T: PcmSample + 'static>(self) -> Self::Output {
+        scale_frame::<T>
+    }
+}
+
+/// Reader over the raw data bytes of a kept range that applies [`Fades`] on the fly, so faded
+/// output still streams in constant memory.
+pub struct FadeReader<R> {
+    inner: R,
+    fades: Fades,
+    frames: u64,
+    /// Frames already read from `inner`.
+    frame: u64,
+    block_align: usize,
+    sample_len: usize,
+    scale: fn(&mut [u8], usize, f64),
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> FadeReader<R> {
+    /// Wraps `inner`, which yields `frames` whole frames laid out as described by `spec`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `spec` is not a sample format that can be faded.
+    pub fn new(inner: R, spec: WavSpec, frames: u64, fades: Fades) -> Result<Self> {
+        let scale = with_sample_type(spec, ScaleFrame)?;
+        let sample_len = spec.bits_per_sample as usize / 8;
+        Ok(Self {
+            inner,
+            fades,
+            frames,
+            frame: 0,
+            block_align: sample_len * spec.channels as usize,
+            sample_len,
+            scale,
+            buf: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    /// Reads the next run of whole frames from `inner` and fades the ones inside a fade.
+    fn refill(&mut self) -> io::Result<()> {
+        let count = CHUNK_FRAMES.min(self.frames - self.frame);
+        self.buf.resize(count as usize * self.block_align, 0);
+        self.inner.read_exact(&mut self.buf)?;
+        self.pos = 0;
+
+        let fade_out_start = self.frames.saturating_sub(self.fades.fade_out);
+        for (i, frame) in self.buf.chunks_exact_mut(self.block_align).enumerate() {
+            let index = self.frame + i as u64;
+            if index < self.fades.fade_in || index >= fade_out_start {
+                (self.scale)(frame, self.sample_len, self.fades.gain(index, self.frames));
+            }
+        }
+        self.frame += count;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for FadeReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            if self.frame == self.frames {
+                return Ok(0);
+            }
+            self.refill()?;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::fixtures::spec;
+    use hound::SampleFormat;
+
+    #[test]
+    fn test_curves() {
+        for curve in [
+            FadeCurve::Linear,
+            FadeCurve::EqualPower,
+            FadeCurve::RaisedCosine,
+        ] {
+            assert_eq!(curve.gain(0.0), 0.0);
+            assert!((curve.gain(1.0) - 1.0).abs() < 1e-12);
+        }
+        assert_eq!(FadeCurve::Linear.gain(0.25), 0.25);
+        // Equal power: the squared gains of a fade-in and a mirrored fade-out sum to one.
+        let (a, b) = (
+            FadeCurve::EqualPower.gain(0.3),
+            FadeCurve::EqualPower.gain(0.7),
+        );
+        assert!((a * a + b * b - 1.0).abs() < 1e-12);
+        assert!((FadeCurve::RaisedCosine.gain(0.5) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fade_reader_scales_only_fade_regions() {
+        // Stereo 16-bit, constant full-ish level; fade in over 4 frames, out over 2.
+        let frames = 10_000u64;
+        let data: Vec<u8> = (0..frames * 2)
+            .flat_map(|_| 16_000i16.to_le_bytes())
+            .collect();
+        let fades = Fades {
+            fade_in: 4,
+            fade_out: 2,
+            curve: FadeCurve::Linear,
+        };
+        let mut reader =
+            FadeReader::new(&data[..], spec(2, 16, SampleFormat::Int), frames, fades).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out.len(), data.len());
+
+        let samples: Vec<i16> = out
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(
+            &samples[..8],
+            &[0, 0, 4000, 4000, 8000, 8000, 12_000, 12_000]
+        );
+        assert!(samples[8..samples.len() - 4].iter().all(|&s| s == 16_000));
+        assert_eq!(&samples[samples.len() - 4..], &[8000, 8000, 0, 0]);
+    }
+
+    #[test]
+    fn test_fade_reader_handles_24_bit_and_overlapping_fades() {
+        // Fades longer than the audio overlap and multiply.
+        let data: Vec<u8> = (0..4).flat_map(|_| [0x00, 0x00, 0x80]).collect();
+        let fades = Fades {
+            fade_in: 8,
+            fade_out: 8,
+            curve: FadeCurve::Linear,
+        };
+        let mut reader =
+            FadeReader::new(&data[..], spec(1, 24, SampleFormat::Int), 4, fades).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        let samples: Vec<i32> = out.chunks_exact(3).map(i32::decode).collect();
+        let full = -(1 << 23) as f64;
+        let expected: Vec<i32> = (0..4)
+            .map(|i| (full * (i as f64 / 8.0) * ((3 - i) as f64 / 8.0)).round() as i32)
+            .collect();
+        assert_eq!(samples, expected);
+    }
+}
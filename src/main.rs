@@ -1,8 +1,37 @@
+mod detect;
+mod detector;
+mod fade;
+mod metadata;
+mod pool;
+mod report;
+mod riff;
+mod source;
+mod splice;
+mod split;
+
 use anyhow::{Context, Result};
-use clap::Parser;
-use hound::{SampleFormat, WavReader, WavWriter};
-use std::fs;
+use clap::{Parser, ValueEnum};
+use detect::{
+    AdaptiveThreshold, ChannelPolicy, Edge, FrameSource, ScanParams, ThresholdReference,
+    ms_to_frame_count, ms_to_frames, reference_level_db, snap_to_zero_crossing, trim_samples,
+};
+use detector::DetectorKind;
+use fade::{FadeCurve, FadeReader, Fades};
+use metadata::{MarkerPolicy, MetadataReport};
+use report::{Record, ReportFormat, ReportWriter};
+use riff::{Malformed, Unsupported, WavLayout};
+use source::{FileSource, PcmSample, SampleTypeVisitor, with_sample_type};
+use splice::{Splice, SpliceReader};
+use split::{ManifestEntry, SplitParams};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 use walkdir::WalkDir;
 
 /// CLI arguments for the wav-files-trim tool.
@@ -15,12 +44,797 @@ struct Args {
     /// Input directory containing WAV files (processed recursively).
     input_dir: String,
 
-    /// Output directory for trimmed WAV files (mirrors input structure).
-    output_dir: String,
+    /// Output directory for trimmed WAV files (mirrors input structure; not needed with
+    /// --dry-run).
+    #[arg(required_unless_present = "dry_run")]
+    output_dir: Option<String>,
 
     /// Silence detection threshold in dBFS (default: -50.0; higher values trim more aggressively).
-    #[arg(short, long, default_value_t = -50.0)]
+    /// With --release-db this is the trigger level.
+    #[arg(
+        short,
+        long,
+        visible_alias = "trigger-db",
+        allow_hyphen_values = true,
+        default_value_t = -50.0
+    )]
     threshold: f64,
+
+    /// Threshold in dBFS for the start search only, overriding --threshold there.
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["adaptive_threshold", "threshold_relative", "only_end"]
+    )]
+    start_threshold: Option<f64>,
+
+    /// Threshold in dBFS for the end search only, overriding --threshold there.
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["adaptive_threshold", "threshold_relative", "only_start"]
+    )]
+    end_threshold: Option<f64>,
+
+    /// Trim leading silence only; the end of each file is kept as is.
+    #[arg(long, conflicts_with = "only_end")]
+    only_start: bool,
+
+    /// Trim trailing silence only; the start of each file is kept as is.
+    #[arg(long)]
+    only_end: bool,
+
+    /// Lower level in dBFS that cut points extend outward to from the triggered windows, keeping
+    /// soft onsets and decaying tails (hysteresis).
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with_all = ["adaptive_threshold", "threshold_relative"]
+    )]
+    release_db: Option<f64>,
+
+    /// Threshold in dB relative to each file's own level (see --relative-to) instead of full
+    /// scale, e.g. `-40`.
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with = "adaptive_threshold"
+    )]
+    threshold_relative: Option<f64>,
+
+    /// Level that --threshold-relative is measured against.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ThresholdReference::Peak,
+        requires = "threshold_relative"
+    )]
+    relative_to: ThresholdReference,
+
+    /// Derive each file's threshold from its estimated noise floor instead of using --threshold.
+    #[arg(long)]
+    adaptive_threshold: bool,
+
+    /// Percentile of window levels taken as the noise floor in adaptive mode.
+    #[arg(long, default_value_t = 10.0, requires = "adaptive_threshold")]
+    noise_percentile: f64,
+
+    /// How far above the noise floor the adaptive threshold is placed, in dB.
+    #[arg(long, default_value_t = 10.0, requires = "adaptive_threshold")]
+    noise_margin_db: f64,
+
+    /// Lowest threshold adaptive mode may choose, in dBFS.
+    #[arg(long, allow_hyphen_values = true, requires = "adaptive_threshold")]
+    threshold_floor: Option<f64>,
+
+    /// Highest threshold adaptive mode may choose, in dBFS.
+    #[arg(long, allow_hyphen_values = true, requires = "adaptive_threshold")]
+    threshold_ceiling: Option<f64>,
+
+    /// How channels are combined for silence detection: `max`, `mean`, or a channel index (e.g. `0`).
+    #[arg(short, long, default_value = "max")]
+    channel_policy: ChannelPolicy,
+
+    /// How each detection window is measured against the threshold.
+    #[arg(long, value_enum, default_value_t = DetectorKind::Rms)]
+    detector: DetectorKind,
+
+    /// Detection window length in milliseconds (converted to samples per file sample rate).
+    #[arg(short, long, default_value_t = 50.0)]
+    window_ms: f64,
+
+    /// How far the detection window advances per step, in milliseconds (at most --window-ms;
+    /// default: 10, or --window-ms if that is shorter).
+    #[arg(long)]
+    hop_ms: Option<f64>,
+
+    /// Envelope length in milliseconds used to refine cut points to the exact sample (0 compares
+    /// individual samples).
+    #[arg(long, default_value_t = 1.0)]
+    refine_ms: f64,
+
+    /// Only treat audio as sound once it stays above the threshold for this many milliseconds,
+    /// so isolated clicks and pops in the silence are trimmed.
+    #[arg(long, default_value_t = 0.0)]
+    min_sound_ms: f64,
+
+    /// Fraction of the windows spanning --min-sound-ms that must be above the threshold (1.0
+    /// requires all of them; lower values tolerate short dropouts).
+    #[arg(long, default_value_t = 1.0)]
+    min_sound_fraction: f64,
+
+    /// Extra audio in milliseconds to keep before the detected start (clamped to the file).
+    #[arg(long, default_value_t = 0.0)]
+    pad_start_ms: f64,
+
+    /// Extra audio in milliseconds to keep after the detected end (clamped to the file).
+    #[arg(long, default_value_t = 0.0)]
+    pad_end_ms: f64,
+
+    /// Never trim more than this many milliseconds from the start; files that hit the limit are
+    /// listed in the summary.
+    #[arg(long)]
+    max_trim_start_ms: Option<f64>,
+
+    /// Never trim more than this many milliseconds from the end; files that hit the limit are
+    /// listed in the summary.
+    #[arg(long)]
+    max_trim_end_ms: Option<f64>,
+
+    /// Move each cut to the nearest zero crossing (or quietest frame, for multichannel audio)
+    /// within this many milliseconds; 0 disables snapping.
+    #[arg(long, default_value_t = 0.0)]
+    snap_radius_ms: f64,
+
+    /// Fade the start of the kept audio in over this many milliseconds.
+    #[arg(long, default_value_t = 0.0)]
+    fade_in_ms: f64,
+
+    /// Fade the end of the kept audio out over this many milliseconds.
+    #[arg(long, default_value_t = 0.0)]
+    fade_out_ms: f64,
+
+    /// Gain curve used for fades.
+    #[arg(long, value_enum, default_value_t = FadeCurve::Linear)]
+    fade_curve: FadeCurve,
+
+    /// Drop metadata chunks (LIST, bext, iXML, cue, ...) instead of copying them to the output.
+    #[arg(long)]
+    strip_metadata: bool,
+
+    /// What to do with cue markers and loops that fall inside trimmed-off audio.
+    #[arg(long, value_enum, default_value_t = MarkerPolicy::Drop)]
+    marker_policy: MarkerPolicy,
+
+    /// What to write for files that are entirely silent or shorter than --min-output-ms after
+    /// trimming.
+    #[arg(long, value_enum, default_value_t = SilentPolicy::Empty)]
+    on_silent: SilentPolicy,
+
+    /// Treat trimmed output shorter than this many milliseconds like an all-silent file.
+    #[arg(long, default_value_t = 0.0)]
+    min_output_ms: f64,
+
+    /// Shorten silent pauses inside the kept audio that are longer than this many milliseconds.
+    #[arg(long)]
+    max_pause_ms: Option<f64>,
+
+    /// Length in milliseconds that long pauses are shortened to (default: --max-pause-ms).
+    #[arg(long, requires = "max_pause_ms")]
+    pause_target_ms: Option<f64>,
+
+    /// Crossfade in milliseconds across each shortened pause.
+    #[arg(long, default_value_t = 10.0, requires = "max_pause_ms")]
+    pause_crossfade_ms: f64,
+
+    /// Shorten pauses without trimming silence from either edge.
+    #[arg(
+        long,
+        requires = "max_pause_ms",
+        conflicts_with_all = ["only_start", "only_end", "start_threshold", "end_threshold"]
+    )]
+    pauses_only: bool,
+
+    /// Split each file at silent gaps into numbered segment files (`name_0001.wav`, ...)
+    /// instead of writing one trimmed file. Files rejected by --on-silent write no segments.
+    #[arg(long, conflicts_with = "max_pause_ms")]
+    split: bool,
+
+    /// Shortest silent gap, in milliseconds, that separates two segments.
+    #[arg(long, default_value_t = 300.0, requires = "split")]
+    min_gap_ms: f64,
+
+    /// Merge segments shorter than this many milliseconds into a neighbouring segment.
+    #[arg(long, default_value_t = 0.0, requires = "split")]
+    min_segment_ms: f64,
+
+    /// Cut segments longer than this many milliseconds at their quietest point.
+    #[arg(long, requires = "split")]
+    max_segment_ms: Option<f64>,
+
+    /// Write the segment manifest to this path (default: `manifest.csv` in the output
+    /// directory; JSON Lines unless the path ends in `.csv`).
+    #[arg(long, value_name = "PATH", requires = "split")]
+    manifest: Option<PathBuf>,
+
+    /// Number of files to trim concurrently (default: number of CPUs).
+    #[arg(short, long)]
+    jobs: Option<NonZeroUsize>,
+
+    /// Report where each file would be cut without writing any audio or creating directories.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write one record per input file to this path (JSON Lines, or CSV for a `.csv` path).
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Report encoding, overriding the one implied by the --report extension.
+    #[arg(long, value_enum, requires = "report")]
+    report_format: Option<ReportFormat>,
+}
+
+/// Files queued per worker thread; bounds how far directory walking runs ahead of trimming.
+const QUEUE_DEPTH_PER_JOB: usize = 4;
+/// Hop length in milliseconds when --hop-ms is not given and the window is at least as long.
+const DEFAULT_HOP_MS: f64 = 10.0;
+
+/// Settings applied to every file processed in a run.
+#[derive(Clone, Debug)]
+pub struct TrimOptions {
+    /// dBFS threshold for silence detection (negative value).
+    pub threshold_db: f64,
+    /// Threshold in dBFS for the start search, overriding `threshold_db` there.
+    pub start_threshold_db: Option<f64>,
+    /// Threshold in dBFS for the end search, overriding `threshold_db` there.
+    pub end_threshold_db: Option<f64>,
+    /// Search for and trim leading silence.
+    pub trim_start: bool,
+    /// Search for and trim trailing silence.
+    pub trim_end: bool,
+    /// Release level in dBFS for hysteresis; `None` uses `threshold_db` only.
+    pub release_db: Option<f64>,
+    /// Threshold in dB relative to each file's `relative_to` level, instead of `threshold_db`.
+    pub threshold_relative_db: Option<f64>,
+    /// Level that `threshold_relative_db` is measured against.
+    pub relative_to: ThresholdReference,
+    /// Derive the threshold from each file's noise floor instead of using `threshold_db`.
+    pub adaptive: Option<AdaptiveThreshold>,
+    /// How the channels of each frame are combined for detection.
+    pub policy: ChannelPolicy,
+    /// How each detection window is measured against the threshold.
+    pub detector: DetectorKind,
+    /// Detection window length in milliseconds.
+    pub window_ms: f64,
+    /// Detection window hop in milliseconds.
+    pub hop_ms: f64,
+    /// Envelope length in milliseconds for sample-accurate refinement.
+    pub refine_ms: f64,
+    /// Minimum duration of sound that stops the start and end searches, in milliseconds.
+    pub min_sound_ms: f64,
+    /// Fraction of windows in a minimum-duration run that must be above the threshold.
+    pub min_sound_fraction: f64,
+    /// Pre-roll kept before the detected start, in milliseconds.
+    pub pad_start_ms: f64,
+    /// Post-roll kept after the detected end, in milliseconds.
+    pub pad_end_ms: f64,
+    /// Most audio that may be trimmed from the start, in milliseconds.
+    pub max_trim_start_ms: Option<f64>,
+    /// Most audio that may be trimmed from the end, in milliseconds.
+    pub max_trim_end_ms: Option<f64>,
+    /// Zero-crossing search radius around each cut, in milliseconds (0 to disable).
+    pub snap_radius_ms: f64,
+    /// Fade-in length at the start of the output, in milliseconds.
+    pub fade_in_ms: f64,
+    /// Fade-out length at the end of the output, in milliseconds.
+    pub fade_out_ms: f64,
+    /// Gain curve of both fades.
+    pub fade_curve: FadeCurve,
+    /// Drop all non-audio chunks instead of copying them to the output.
+    pub strip_metadata: bool,
+    /// Handling of markers and loops that fall inside removed audio.
+    pub marker_policy: MarkerPolicy,
+    /// Output for files that are all silent or shorter than `min_output_ms`.
+    pub on_silent: SilentPolicy,
+    /// Shortest trimmed output written normally, in milliseconds.
+    pub min_output_ms: f64,
+    /// Shorten internal pauses longer than this, in milliseconds; `None` leaves them alone.
+    pub max_pause_ms: Option<f64>,
+    /// Length that long pauses are shortened to, in milliseconds.
+    pub pause_target_ms: f64,
+    /// Crossfade across each shortened pause, in milliseconds.
+    pub pause_crossfade_ms: f64,
+    /// Split the kept audio at silent gaps into segments instead of writing it whole.
+    pub split: bool,
+    /// Shortest gap that separates two segments, in milliseconds.
+    pub min_gap_ms: f64,
+    /// Shortest segment, in milliseconds; shorter ones are merged into a neighbour.
+    pub min_segment_ms: f64,
+    /// Longest segment, in milliseconds; `None` for no limit.
+    pub max_segment_ms: Option<f64>,
+}
+
+impl From<&Args> for TrimOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            threshold_db: args.threshold,
+            start_threshold_db: args.start_threshold,
+            end_threshold_db: args.end_threshold,
+            trim_start: !args.only_end && !args.pauses_only,
+            trim_end: !args.only_start && !args.pauses_only,
+            release_db: args.release_db,
+            threshold_relative_db: args.threshold_relative,
+            relative_to: args.relative_to,
+            adaptive: args.adaptive_threshold.then_some(AdaptiveThreshold {
+                percentile: args.noise_percentile,
+                margin_db: args.noise_margin_db,
+                min_db: args.threshold_floor,
+                max_db: args.threshold_ceiling,
+            }),
+            policy: args.channel_policy,
+            detector: args.detector,
+            window_ms: args.window_ms,
+            hop_ms: args.hop_ms.unwrap_or(DEFAULT_HOP_MS.min(args.window_ms)),
+            refine_ms: args.refine_ms,
+            min_sound_ms: args.min_sound_ms,
+            min_sound_fraction: args.min_sound_fraction,
+            pad_start_ms: args.pad_start_ms,
+            pad_end_ms: args.pad_end_ms,
+            max_trim_start_ms: args.max_trim_start_ms,
+            max_trim_end_ms: args.max_trim_end_ms,
+            snap_radius_ms: args.snap_radius_ms,
+            fade_in_ms: args.fade_in_ms,
+            fade_out_ms: args.fade_out_ms,
+            fade_curve: args.fade_curve,
+            strip_metadata: args.strip_metadata,
+            marker_policy: args.marker_policy,
+            on_silent: args.on_silent,
+            min_output_ms: args.min_output_ms,
+            max_pause_ms: args.max_pause_ms,
+            pause_target_ms: args
+                .pause_target_ms
+                .or(args.max_pause_ms)
+                .unwrap_or_default(),
+            pause_crossfade_ms: args.pause_crossfade_ms,
+            split: args.split,
+            min_gap_ms: args.min_gap_ms,
+            min_segment_ms: args.min_segment_ms,
+            max_segment_ms: args.max_segment_ms,
+        }
+    }
+}
+
+/// What to do with a file whose trimmed audio is empty or too short.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SilentPolicy {
+    /// Write nothing for the file.
+    Skip,
+    /// Write the original audio untrimmed and without fades.
+    Copy,
+    /// Write a WAV file with no audio frames.
+    #[default]
+    Empty,
+    /// Fail the file.
+    Error,
+}
+
+/// Why a file's trimmed audio was not written as detected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// No audio is above the threshold.
+    Silent,
+    /// The kept audio is shorter than the minimum output length.
+    TooShort,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rejection::Silent => "all silent",
+            Rejection::TooShort => "shorter than the minimum output length",
+        })
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// Silence detection result for a single file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// Sample rate of the input in Hz.
+    pub sample_rate: u32,
+    /// Number of interleaved channels.
+    pub channels: u16,
+    /// Whole frames in the input's data chunk.
+    pub total_frames: u64,
+    /// Frames kept after trimming, padding and snapping; empty if the file is entirely silent.
+    pub keep: Range<u64>,
+    /// Frames written to the output: `keep` with any long pauses shortened, or other frames
+    /// when the file is rejected; `None` if nothing is written.
+    pub output: Option<Splice>,
+    /// Input frames written to each segment file when splitting, in order; `None` if not
+    /// splitting.
+    pub segments: Option<Vec<Range<u64>>>,
+    /// Why `keep` was not written as is, if it was not.
+    pub rejection: Option<Rejection>,
+    /// Kept frames after padding, before snapping to zero crossings.
+    pub padded: Range<u64>,
+    /// Sample-accurate cut points before padding.
+    pub detected: Range<u64>,
+    /// Cut points before sample-accurate refinement, quantized to the detection hop.
+    pub coarse: Range<u64>,
+    /// Detection threshold used for this file, in dBFS.
+    pub threshold_db: f64,
+    /// Threshold the start was searched with, in dBFS; `None` if the start was not trimmed.
+    pub start_threshold_db: Option<f64>,
+    /// Threshold the end was searched with, in dBFS; `None` if the end was not trimmed.
+    pub end_threshold_db: Option<f64>,
+    /// Hysteresis release level in dBFS, if used.
+    pub release_db: Option<f64>,
+    /// Estimated noise floor in dBFS, if the threshold was derived from it.
+    pub noise_floor_db: Option<f64>,
+    /// Measured peak or loudest-window level in dBFS, if the threshold is relative to it.
+    pub reference_db: Option<f64>,
+    /// Detection would have trimmed more than the start limit allows.
+    pub capped_start: bool,
+    /// Detection would have trimmed more than the end limit allows.
+    pub capped_end: bool,
+}
+
+impl Analysis {
+    /// Converts a frame count or position to seconds at this file's sample rate.
+    pub fn seconds(&self, frames: u64) -> f64 {
+        frames as f64 / self.sample_rate as f64
+    }
+
+    /// Returns the padding actually added before and after the detected audio, in frames, after
+    /// clamping to the file bounds.
+    pub fn padding(&self) -> (u64, u64) {
+        (
+            self.detected.start - self.padded.start,
+            self.padded.end - self.detected.end,
+        )
+    }
+
+    /// Returns the number of frames written, across all segment files when splitting.
+    pub fn output_frames(&self) -> u64 {
+        match &self.segments {
+            Some(segments) => segments.iter().map(|s| s.end - s.start).sum(),
+            None => self.output.as_ref().map_or(0, Splice::frames),
+        }
+    }
+
+    /// Returns how many frames each cut moved when snapped to a zero crossing; positive values
+    /// are later in the file.
+    pub fn snap_shift(&self) -> (i64, i64) {
+        (
+            self.keep.start as i64 - self.padded.start as i64,
+            self.keep.end as i64 - self.padded.end as i64,
+        )
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "duration {:.3}s ({} samples), start {} ({:.3}s), end {} ({:.3}s), trimmed duration {:.3}s, \
+             coarse start {}, coarse end {}",
+            self.seconds(self.total_frames),
+            self.total_frames,
+            self.keep.start,
+            self.seconds(self.keep.start),
+            self.keep.end,
+            self.seconds(self.keep.end),
+            self.seconds(self.keep.end - self.keep.start),
+            self.coarse.start,
+            self.coarse.end,
+        )?;
+        if self.detected != self.keep {
+            write!(
+                f,
+                ", detected start {}, detected end {}",
+                self.detected.start, self.detected.end
+            )?;
+        }
+        let (pad_start, pad_end) = self.padding();
+        if pad_start > 0 || pad_end > 0 {
+            write!(
+                f,
+                ", padded {:.3}s before and {:.3}s after",
+                self.seconds(pad_start),
+                self.seconds(pad_end)
+            )?;
+        }
+        for (edge, threshold_db) in [
+            ("start", self.start_threshold_db),
+            ("end", self.end_threshold_db),
+        ] {
+            match threshold_db {
+                None => write!(f, ", {} not trimmed", edge)?,
+                Some(db) if db != self.threshold_db => {
+                    write!(f, ", {} threshold {:.1} dBFS", edge, db)?
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(release_db) = self.release_db {
+            write!(
+                f,
+                ", trigger {:.1} dBFS, release {:.1} dBFS",
+                self.threshold_db, release_db
+            )?;
+        }
+        if let Some(noise_floor_db) = self.noise_floor_db {
+            write!(
+                f,
+                ", threshold {:.1} dBFS from noise floor {:.1} dBFS",
+                self.threshold_db, noise_floor_db
+            )?;
+        }
+        if let Some(reference_db) = self.reference_db {
+            write!(
+                f,
+                ", threshold {:.1} dBFS relative to reference level {:.1} dBFS",
+                self.threshold_db, reference_db
+            )?;
+        }
+        if self.capped_start || self.capped_end {
+            let edges = match (self.capped_start, self.capped_end) {
+                (true, true) => "start and end",
+                (true, false) => "start",
+                _ => "end",
+            };
+            write!(f, ", {} capped at maximum trim", edges)?;
+        }
+        if let Some(output) = &self.output
+            && output.joins() > 0
+        {
+            write!(
+                f,
+                ", shortened {} pauses by {:.3}s",
+                output.joins(),
+                self.seconds(output.removed())
+            )?;
+        }
+        if let Some(segments) = &self.segments {
+            write!(f, ", split into {} segments", segments.len())?;
+        }
+        if let Some(rejection) = self.rejection {
+            let action = match &self.output {
+                None => "skipped",
+                Some(output) if output.frames() == 0 => "written empty",
+                Some(_) => "copied unchanged",
+            };
+            write!(f, ", {}, {}", rejection, action)?;
+        }
+        let (snap_start, snap_end) = self.snap_shift();
+        if snap_start != 0 || snap_end != 0 {
+            write!(
+                f,
+                ", snapped start by {:+} and end by {:+} samples",
+                snap_start, snap_end
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of trimming a single file.
+#[derive(Clone, Debug)]
+pub struct TrimOutcome {
+    /// Where the file was cut.
+    pub analysis: Analysis,
+    /// Metadata positions adjusted to match the cut.
+    pub metadata: MetadataReport,
+}
+
+/// Runs silence detection on a WAV file without writing anything.
+///
+/// # Errors
+///
+/// Returns an error if the file format is unsupported or I/O fails, or a [`Rejection`] if the
+/// file is all silent or too short and `options.on_silent` is [`SilentPolicy::Error`].
+pub fn analyze_wav(input_path: &Path, options: &TrimOptions) -> Result<Analysis> {
+    analyze(input_path, options).map(|(analysis, _, _)| analysis)
+}
+
+/// Opens and parses a WAV file and runs detection, returning the result along with the parsed
+/// layout and the open file for copying.
+fn analyze(input_path: &Path, options: &TrimOptions) -> Result<(Analysis, WavLayout, File)> {
+    let mut input = File::open(input_path).context("Failed to open input WAV file")?;
+    let layout = WavLayout::read(&mut input).context(Malformed)?;
+    let spec = layout.spec()?;
+
+    if let ChannelPolicy::Channel(ch) = options.policy
+        && ch >= spec.channels as usize
+    {
+        anyhow::bail!(Unsupported(format!(
+            "channel layout: channel {} requested for detection, but file has {} channel(s)",
+            ch, spec.channels
+        )));
+    }
+
+    let window_size = ms_to_frames(options.window_ms, spec.sample_rate);
+    // Rounding to frames must not push the hop past the window.
+    let hop_size = ms_to_frames(options.hop_ms, spec.sample_rate).min(window_size);
+    let refine_size = ms_to_frames(options.refine_ms, spec.sample_rate);
+    let mut source = open_source(&input, &layout, options.policy)?;
+    let mut detector = options.detector.build(spec.sample_rate, options.policy);
+    let mut noise_floor_db = None;
+    let mut reference_db = None;
+    let threshold_db = if let Some(adaptive) = &options.adaptive {
+        noise_floor_db = detect::noise_floor_db(
+            source.as_mut(),
+            detector.as_mut(),
+            window_size,
+            adaptive.percentile,
+        )?;
+        noise_floor_db.map_or(options.threshold_db, |floor| adaptive.threshold_db(floor))
+    } else if let Some(relative_db) = options.threshold_relative_db {
+        let reference = reference_level_db(
+            source.as_mut(),
+            detector.as_mut(),
+            window_size,
+            options.relative_to,
+        )?;
+        reference_db = Some(reference);
+        reference + relative_db
+    } else {
+        options.threshold_db
+    };
+    let start_threshold_db = options
+        .trim_start
+        .then(|| options.start_threshold_db.unwrap_or(threshold_db));
+    let end_threshold_db = options
+        .trim_end
+        .then(|| options.end_threshold_db.unwrap_or(threshold_db));
+    let max_trim_start = options
+        .max_trim_start_ms
+        .map(|ms| ms_to_frame_count(ms, spec.sample_rate));
+    let max_trim_end = options
+        .max_trim_end_ms
+        .map(|ms| ms_to_frame_count(ms, spec.sample_rate));
+    let params = ScanParams {
+        start_db: start_threshold_db,
+        end_db: end_threshold_db,
+        refine_size,
+        max_trim_start: max_trim_start.map(|frames| frames as usize),
+        max_trim_end: max_trim_end.map(|frames| frames as usize),
+        release_db: options.release_db,
+        ..ScanParams::new(threshold_db, window_size, hop_size)
+    }
+    .with_min_sound(
+        ms_to_frame_count(options.min_sound_ms, spec.sample_rate) as usize,
+        options.min_sound_fraction,
+    );
+    let cuts = trim_samples(source.as_mut(), detector.as_mut(), &params)?;
+
+    let total_frames = layout.data_len / layout.block_align()?;
+    let detected = cuts.refined.start as u64..cuts.refined.end as u64;
+    let padded = if detected.is_empty() {
+        detected.clone()
+    } else {
+        let pad_start = ms_to_frame_count(options.pad_start_ms, spec.sample_rate);
+        let pad_end = ms_to_frame_count(options.pad_end_ms, spec.sample_rate);
+        detected.start.saturating_sub(pad_start)..(detected.end + pad_end).min(total_frames)
+    };
+
+    let snap_radius = ms_to_frame_count(options.snap_radius_ms, spec.sample_rate) as usize;
+    let mut keep = padded.clone();
+    if snap_radius > 0 && !keep.is_empty() {
+        let start = snap_to_zero_crossing(
+            source.as_mut(),
+            keep.start as usize,
+            snap_radius,
+            Edge::Start,
+        )?;
+        let end =
+            snap_to_zero_crossing(source.as_mut(), keep.end as usize, snap_radius, Edge::End)?;
+        if start < end {
+            keep = start as u64..end as u64;
+        }
+        // Snapping must not trim past the limits either.
+        if let Some(max) = max_trim_start {
+            keep.start = keep.start.min(max);
+        }
+        if let Some(max) = max_trim_end {
+            keep.end = keep.end.max(total_frames.saturating_sub(max));
+        }
+    }
+
+    let splice = match options.max_pause_ms {
+        Some(max_pause_ms) if !keep.is_empty() => {
+            let pauses = detect::find_pauses(
+                source.as_mut(),
+                detector.as_mut(),
+                keep.start as usize..keep.end as usize,
+                threshold_db,
+                window_size,
+                hop_size,
+                ms_to_frame_count(max_pause_ms, spec.sample_rate) as usize,
+            )?;
+            let pauses: Vec<_> = pauses
+                .into_iter()
+                .map(|p| p.start as u64..p.end as u64)
+                .collect();
+            Splice::collapse(
+                keep.clone(),
+                &pauses,
+                ms_to_frame_count(options.pause_target_ms, spec.sample_rate),
+                ms_to_frame_count(options.pause_crossfade_ms, spec.sample_rate),
+            )
+        }
+        _ => Splice::new(keep.clone()),
+    };
+
+    let min_output = ms_to_frame_count(options.min_output_ms, spec.sample_rate);
+    let rejection = if keep.is_empty() {
+        Some(Rejection::Silent)
+    } else if splice.frames() < min_output {
+        Some(Rejection::TooShort)
+    } else {
+        None
+    };
+    let output = match (rejection, options.on_silent) {
+        (None, _) => Some(splice),
+        (Some(rejection), SilentPolicy::Error) => anyhow::bail!(rejection),
+        (Some(_), SilentPolicy::Skip) => None,
+        (Some(_), SilentPolicy::Copy) => Some(Splice::new(0..total_frames)),
+        (Some(_), SilentPolicy::Empty) => Some(Splice::new(keep.start..keep.start)),
+    };
+    let segments = match &output {
+        _ if !options.split => None,
+        Some(_) if rejection.is_none() => {
+            let frames = |ms| ms_to_frame_count(ms, spec.sample_rate) as usize;
+            let params = SplitParams {
+                min_gap: frames(options.min_gap_ms),
+                min_segment: frames(options.min_segment_ms),
+                max_segment: options.max_segment_ms.map(frames),
+                pad_start: frames(options.pad_start_ms),
+                pad_end: frames(options.pad_end_ms),
+            };
+            let segments = split::split_segments(
+                source.as_mut(),
+                detector.as_mut(),
+                keep.start as usize..keep.end as usize,
+                threshold_db,
+                window_size,
+                hop_size,
+                &params,
+            )?;
+            Some(
+                segments
+                    .into_iter()
+                    .map(|s| s.start as u64..s.end as u64)
+                    .collect(),
+            )
+        }
+        // Rejected files are split into nothing, whatever the policy, and only reported.
+        _ => Some(Vec::new()),
+    };
+
+    let analysis = Analysis {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        total_frames,
+        keep,
+        output,
+        segments,
+        rejection,
+        padded,
+        detected,
+        coarse: cuts.coarse.start as u64..cuts.coarse.end as u64,
+        threshold_db,
+        start_threshold_db,
+        end_threshold_db,
+        release_db: options.release_db,
+        noise_floor_db,
+        reference_db,
+        capped_start: cuts.capped_start,
+        capped_end: cuts.capped_end,
+    };
+    Ok((analysis, layout, input))
 }
 
 /// Trims leading and trailing silence from a WAV file based on RMS over a sliding window.
@@ -29,186 +843,1183 @@ struct Args {
 ///
 /// * `input_path` - Path to the input WAV file.
 /// * `output_path` - Path to write the trimmed WAV file.
-/// * `threshold_db` - dBFS threshold for silence detection (negative value).
+/// * `options` - Detection and output settings.
 ///
 /// # Errors
 ///
-/// Returns an error if the file format is unsupported or I/O fails.
-pub fn trim_wav(input_path: &Path, output_path: &Path, threshold_db: f64) -> Result<()> {
-    let mut reader = WavReader::open(input_path).context("Failed to open input WAV file")?;
-    let spec = reader.spec();
-
-    // Validate format as per project context (mono, 16-bit PCM, 16kHz).
-    if spec.channels != 1
-        || spec.sample_rate != 16_000
-        || spec.bits_per_sample != 16
-        || spec.sample_format != SampleFormat::Int
-    {
-        anyhow::bail!("Unsupported WAV format: expected mono 16-bit PCM at 16kHz");
-    }
+/// Returns an error if the file format is unsupported or I/O fails, or a [`Rejection`] if the
+/// file is all silent or too short and `options.on_silent` is [`SilentPolicy::Error`].
+///
+/// The kept audio is copied byte-for-byte, so the output has the same sample format and bit
+/// depth as the input; only samples inside `options.fade_in_ms`/`fade_out_ms` are rewritten.
+/// Non-audio chunks are carried over in their original order unless `options.strip_metadata` is
+/// set, with time-anchored positions shifted to match the trimmed audio; the returned outcome
+/// reports where the file was cut and what metadata was adjusted. Files that are all silent or
+/// shorter than `options.min_output_ms` are skipped, copied or written empty following
+/// `options.on_silent`.
+pub fn trim_wav(
+    input_path: &Path,
+    output_path: &Path,
+    options: &TrimOptions,
+) -> Result<TrimOutcome> {
+    let (analysis, mut layout, input) = analyze(input_path, options)?;
+    let Some(splice) = analysis.output.clone() else {
+        return Ok(TrimOutcome {
+            analysis,
+            metadata: MetadataReport::default(),
+        });
+    };
 
-    let samples: Vec<i16> = reader
-        .samples::<i16>()
-        .collect::<Result<Vec<_>, hound::Error>>()
-        .context("Failed to read samples")?;
+    if options.strip_metadata {
+        layout.strip_metadata();
+    }
+    let fades = output_fades(&analysis, options);
+    let metadata = write_output(
+        &input,
+        &mut layout,
+        &splice,
+        fades,
+        options.marker_policy,
+        output_path,
+    )?;
+    Ok(TrimOutcome { analysis, metadata })
+}
 
-    let trimmed_samples = trim_samples(&samples, threshold_db, 800)?; // 50ms window at 16kHz
+/// Result of splitting a single file.
+#[derive(Clone, Debug)]
+pub struct SplitOutcome {
+    /// Where the file was cut and split.
+    pub analysis: Analysis,
+    /// Metadata positions adjusted for each segment file, in order.
+    pub metadata: Vec<MetadataReport>,
+}
 
-    let mut writer =
-        WavWriter::create(output_path, spec).context("Failed to create output WAV file")?;
-    for &sample in &trimmed_samples {
-        writer
-            .write_sample(sample)
-            .context("Failed to write sample")?;
+/// Splits a WAV file at silent gaps inside its kept audio into numbered segment files named
+/// after `output_path` by [`segment_path`].
+///
+/// # Errors
+///
+/// Returns an error if the file format is unsupported or I/O fails, or a [`Rejection`] if the
+/// file is all silent or too short and `options.on_silent` is [`SilentPolicy::Error`].
+///
+/// Each segment is written the way [`trim_wav`] writes its output: copied byte-for-byte, faded
+/// at its own edges, and with the metadata positions shifted to the segment. Files that are all
+/// silent or too short are written as a single segment, or none, following `options.on_silent`.
+pub fn split_wav(
+    input_path: &Path,
+    output_path: &Path,
+    options: &TrimOptions,
+) -> Result<SplitOutcome> {
+    let (analysis, mut layout, input) = analyze(input_path, options)?;
+    if options.strip_metadata {
+        layout.strip_metadata();
+    }
+    let fades = output_fades(&analysis, options);
+    let mut metadata = Vec::new();
+    for (i, segment) in analysis.segments.iter().flatten().enumerate() {
+        metadata.push(write_output(
+            &input,
+            &mut layout.clone(),
+            &Splice::new(segment.clone()),
+            fades,
+            options.marker_policy,
+            &segment_path(output_path, i + 1),
+        )?);
     }
-    writer.finalize().context("Failed to finalize WAV writer")?;
+    Ok(SplitOutcome { analysis, metadata })
+}
 
-    Ok(())
+/// Returns the path of the `index`th (one-based) segment split from the file at `path`:
+/// `name_0001.wav` next to `name.wav`.
+pub fn segment_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_{:04}.wav", stem, index))
 }
 
-/// Computes the RMS value of a slice of i16 samples.
-fn rms(chunk: &[i16]) -> f64 {
-    if chunk.is_empty() {
-        return 0.0;
+/// Returns the fades to apply to each output of `analysis`.
+fn output_fades(analysis: &Analysis, options: &TrimOptions) -> Fades {
+    // Rejected files copied unchanged are not faded.
+    let (fade_in_ms, fade_out_ms) = match analysis.rejection {
+        Some(_) => (0.0, 0.0),
+        None => (options.fade_in_ms, options.fade_out_ms),
+    };
+    Fades {
+        fade_in: ms_to_frame_count(fade_in_ms, analysis.sample_rate),
+        fade_out: ms_to_frame_count(fade_out_ms, analysis.sample_rate),
+        curve: options.fade_curve,
     }
-    let sum_sq: f64 = chunk.iter().map(|&s| (s as f64).powi(2)).sum();
-    (sum_sq / chunk.len() as f64).sqrt()
 }
 
-/// Trims leading/trailing silence from samples using RMS-based detection over a fixed window.
-fn trim_samples(samples: &[i16], threshold_db: f64, window_size: usize) -> Result<Vec<i16>> {
-    let len = samples.len();
-    if len == 0 {
-        return Ok(Vec::new());
-    }
+/// Writes the frames of `splice` from `input` to `output_path` with the chunks of `layout`,
+/// adjusting its metadata positions to match, and returns what was adjusted.
+fn write_output(
+    mut input: &File,
+    layout: &mut WavLayout,
+    splice: &Splice,
+    fades: Fades,
+    policy: MarkerPolicy,
+    output_path: &Path,
+) -> Result<MetadataReport> {
+    let frames = splice.frames();
+    layout.set_fact_sample_length(frames);
+    let metadata = metadata::adjust_positions(layout, splice, policy);
 
-    let full_scale = 32768.0f64;
-    let threshold_linear = 10f64.powf(threshold_db / 20.0);
-    let threshold_rms = threshold_linear * full_scale;
+    let block_align = layout.block_align()?;
+    let mut data: Box<dyn Read + '_> = if splice.joins() == 0 {
+        input
+            .seek(SeekFrom::Start(
+                layout.data_offset + splice.start() * block_align,
+            ))
+            .context("Failed to seek to kept audio")?;
+        Box::new(BufReader::new(input))
+    } else {
+        Box::new(SpliceReader::new(
+            input,
+            layout.data_offset,
+            layout.spec()?,
+            splice,
+        )?)
+    };
+    if !fades.is_none() {
+        data = Box::new(FadeReader::new(data, layout.spec()?, frames, fades)?);
+    }
+    let mut writer =
+        BufWriter::new(File::create(output_path).context("Failed to create output WAV file")?);
+    layout
+        .write(&mut writer, &mut data, frames * block_align)
+        .context("Failed to write output WAV file")?;
+    writer.flush().context("Failed to flush output WAV file")?;
+    Ok(metadata)
+}
 
-    // Find start trim point: first window with RMS above threshold.
-    let mut start_trim = len;
-    for i in (0..len).step_by(window_size) {
-        let chunk_end = (i + window_size).min(len);
-        let chunk_rms = rms(&samples[i..chunk_end]);
-        if chunk_rms > threshold_rms {
-            start_trim = i;
-            break;
-        }
+/// Opens a streaming frame source over the data chunk of `file`, decoding samples in the
+/// layout's format. The source reads through a cloned handle, which shares its position with
+/// `file`, so the source and any later copying from `file` each seek before they read.
+fn open_source(
+    file: &File,
+    layout: &WavLayout,
+    policy: ChannelPolicy,
+) -> Result<Box<dyn FrameSource>> {
+    /// Opens a [`FileSource`] decoding samples as the visited type.
+    struct Open<'a> {
+        file: File,
+        layout: &'a WavLayout,
+        policy: ChannelPolicy,
     }
 
-    // Find end trim point: last window with RMS above threshold.
-    let mut end_trim = 0;
-    for i in (0..=len).rev().step_by(window_size) {
-        let chunk_start = (i.saturating_sub(window_size)).max(0);
-        let chunk_rms = rms(&samples[chunk_start..i]);
-        if chunk_rms > threshold_rms {
-            end_trim = i;
-            break;
+    impl SampleTypeVisitor for Open<'_> {
+        type Output = Result<Box<dyn FrameSource>>;
+
+        fn visit<T: PcmSample + 'static>(self) -> Self::Output {
+            Ok(Box::new(FileSource::<T>::new(
+                self.file,
+                self.layout,
+                self.policy,
+            )?))
         }
     }
 
-    let trimmed = if start_trim < end_trim {
-        samples[start_trim..end_trim].to_vec()
-    } else {
-        Vec::new()
+    let file = file
+        .try_clone()
+        .context("Failed to reopen input WAV file")?;
+    let open = Open {
+        file,
+        layout,
+        policy,
     };
+    with_sample_type(layout.spec()?, open)?
+}
 
-    Ok(trimmed)
+/// A file found while walking the input directory.
+struct Job {
+    input_path: PathBuf,
+    rel_path: PathBuf,
+    /// Where to write the trimmed file; `None` in dry-run mode.
+    output_path: Option<PathBuf>,
+}
+
+/// Returns `true` if `input_path` is a Wave64 file with a `.wav` file of the same name next to
+/// it. Wave64 inputs are written with a `.wav` extension, so the two would share an output file;
+/// the `.wav` file is the one written.
+fn shares_output_with_wav(input_path: &Path) -> bool {
+    input_path.extension().is_some_and(|ext| ext == "w64")
+        && input_path.with_extension("wav").is_file()
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
     let input_dir = Path::new(&args.input_dir);
-    let output_dir = Path::new(&args.output_dir);
+    let output_dir = args.output_dir.as_deref().map(Path::new);
+
+    if args.window_ms.is_nan() || args.window_ms <= 0.0 {
+        anyhow::bail!("Window length must be positive, got {} ms", args.window_ms);
+    }
+    let trigger_db = [
+        (!args.only_end).then(|| args.start_threshold.unwrap_or(args.threshold)),
+        (!args.only_start).then(|| args.end_threshold.unwrap_or(args.threshold)),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(f64::INFINITY, f64::min);
+    if let Some(release_db) = args.release_db
+        && (release_db.is_nan() || release_db > trigger_db)
+    {
+        anyhow::bail!(
+            "Release level must not be above the trigger threshold, got {} dBFS (trigger {} dBFS)",
+            release_db,
+            trigger_db
+        );
+    }
+    if !(0.0..=100.0).contains(&args.noise_percentile) {
+        anyhow::bail!(
+            "Noise percentile must be between 0 and 100, got {}",
+            args.noise_percentile
+        );
+    }
+    if !(args.min_sound_fraction > 0.0 && args.min_sound_fraction <= 1.0) {
+        anyhow::bail!(
+            "Minimum sound fraction must be greater than 0 and at most 1, got {}",
+            args.min_sound_fraction
+        );
+    }
+    if args.refine_ms.is_nan() || args.refine_ms < 0.0 {
+        anyhow::bail!(
+            "Refinement envelope must not be negative, got {} ms",
+            args.refine_ms
+        );
+    }
+    for (name, ms) in [
+        ("Start padding", args.pad_start_ms),
+        ("End padding", args.pad_end_ms),
+        ("Snap radius", args.snap_radius_ms),
+        ("Maximum start trim", args.max_trim_start_ms.unwrap_or(0.0)),
+        ("Maximum end trim", args.max_trim_end_ms.unwrap_or(0.0)),
+        ("Minimum sound duration", args.min_sound_ms),
+        ("Fade-in", args.fade_in_ms),
+        ("Fade-out", args.fade_out_ms),
+        ("Minimum output length", args.min_output_ms),
+        ("Maximum pause", args.max_pause_ms.unwrap_or(0.0)),
+        ("Pause target", args.pause_target_ms.unwrap_or(0.0)),
+        ("Pause crossfade", args.pause_crossfade_ms),
+        ("Minimum gap", args.min_gap_ms),
+        ("Minimum segment length", args.min_segment_ms),
+    ] {
+        if ms.is_nan() || ms < 0.0 {
+            anyhow::bail!("{} must not be negative, got {} ms", name, ms);
+        }
+    }
+    if let (Some(max_pause_ms), Some(target_ms)) = (args.max_pause_ms, args.pause_target_ms)
+        && target_ms > max_pause_ms
+    {
+        anyhow::bail!(
+            "Pause target must not exceed the maximum pause, got {} ms (maximum {} ms)",
+            target_ms,
+            max_pause_ms
+        );
+    }
+    if let Some(max_segment_ms) = args.max_segment_ms
+        && (max_segment_ms.is_nan() || max_segment_ms <= 0.0)
+    {
+        anyhow::bail!(
+            "Maximum segment length must be positive, got {} ms",
+            max_segment_ms
+        );
+    }
+    if let Some(hop_ms) = args.hop_ms
+        && (hop_ms.is_nan() || hop_ms <= 0.0 || hop_ms > args.window_ms)
+    {
+        anyhow::bail!(
+            "Hop length must be positive and at most the window length, got {} ms",
+            hop_ms
+        );
+    }
 
     if !input_dir.exists() {
         anyhow::bail!("Input directory does not exist: {}", args.input_dir);
     }
 
-    fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    // Dry runs never touch the output tree.
+    let output_dir = output_dir.filter(|_| !args.dry_run);
+    if let Some(output_dir) = output_dir {
+        fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    }
 
-    let mut processed = 0;
-    for entry in WalkDir::new(input_dir)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_file()
-            && entry.path().extension().and_then(|ext| ext.to_str()) == Some("wav")
-        {
-            let rel_path = entry
-                .path()
-                .strip_prefix(input_dir)
-                .context("Failed to compute relative path")?;
-            let output_path: PathBuf = output_dir.join(rel_path);
-
-            // Ensure parent directories exist.
-            if let Some(parent) = output_path.parent() {
-                fs::create_dir_all(parent).context("Failed to create output subdirectory")?;
-            }
+    let options = TrimOptions::from(&args);
+    let jobs = args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let processed = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let silent = AtomicUsize::new(0);
+    let too_short = AtomicUsize::new(0);
+    let capped = Mutex::new(Vec::new());
+    let segments = AtomicUsize::new(0);
+    let report = args
+        .report
+        .as_deref()
+        .map(|path| {
+            let format = args
+                .report_format
+                .unwrap_or_else(|| ReportFormat::from_path(path));
+            ReportWriter::create(path, format).map(Mutex::new)
+        })
+        .transpose()?;
+    // Split runs list their segments in a manifest, by default next to them.
+    let manifest_path = args.manifest.clone().or_else(|| {
+        output_dir
+            .filter(|_| args.split)
+            .map(|dir| dir.join("manifest.csv"))
+    });
+    let manifest = manifest_path
+        .as_deref()
+        .map(|path| {
+            ReportWriter::<ManifestEntry>::create(path, ReportFormat::from_path(path))
+                .map(Mutex::new)
+        })
+        .transpose()?;
 
-            if let Err(e) = trim_wav(entry.path(), &output_path, args.threshold) {
-                eprintln!("Error processing {}: {}", entry.path().display(), e);
-            } else {
-                processed += 1;
+    pool::run(
+        jobs,
+        jobs * QUEUE_DEPTH_PER_JOB,
+        |send| {
+            for entry in WalkDir::new(input_dir)
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
+            {
+                if entry.file_type().is_file()
+                    && matches!(
+                        entry.path().extension().and_then(|ext| ext.to_str()),
+                        Some("wav" | "w64")
+                    )
+                {
+                    let rel_path = entry
+                        .path()
+                        .strip_prefix(input_dir)
+                        .context("Failed to compute relative path")?
+                        .to_path_buf();
+                    // Wave64 inputs are written as RIFF/RF64, so they get a `.wav` extension.
+                    let output_path =
+                        output_dir.map(|dir| dir.join(&rel_path).with_extension("wav"));
+
+                    // Ensure parent directories exist.
+                    if let Some(parent) = output_path.as_deref().and_then(Path::parent) {
+                        fs::create_dir_all(parent)
+                            .context("Failed to create output subdirectory")?;
+                    }
+
+                    send(Job {
+                        input_path: entry.into_path(),
+                        rel_path,
+                        output_path,
+                    });
+                }
             }
-        }
+            Ok(())
+        },
+        |job: Job| {
+            let result = match &job.output_path {
+                Some(output_path) if shares_output_with_wav(&job.input_path) => {
+                    Err(anyhow::anyhow!(
+                        "Output file {} is also the output of {}; rename one of the inputs",
+                        output_path.display(),
+                        job.input_path.with_extension("wav").display()
+                    ))
+                }
+                Some(output_path) if options.split => {
+                    split_wav(&job.input_path, output_path, &options).map(|outcome| {
+                        for (i, metadata) in outcome.metadata.iter().enumerate() {
+                            if !metadata.is_empty() {
+                                println!(
+                                    "Adjusted metadata in {}: {}",
+                                    segment_path(&job.rel_path, i + 1).display(),
+                                    metadata
+                                );
+                            }
+                        }
+                        outcome.analysis
+                    })
+                }
+                Some(output_path) => {
+                    trim_wav(&job.input_path, output_path, &options).map(|outcome| {
+                        if !outcome.metadata.is_empty() {
+                            println!(
+                                "Adjusted metadata in {}: {}",
+                                job.rel_path.display(),
+                                outcome.metadata
+                            );
+                        }
+                        outcome.analysis
+                    })
+                }
+                None => analyze_wav(&job.input_path, &options)
+                    .inspect(|analysis| println!("{}: {}", job.rel_path.display(), analysis)),
+            };
+            let rejection = match &result {
+                Ok(analysis) => analysis.rejection,
+                Err(e) => e
+                    .chain()
+                    .find_map(|e| e.downcast_ref::<Rejection>())
+                    .copied(),
+            };
+            // Rejected files are counted on their own, whatever the policy did with them.
+            let count = match rejection {
+                Some(Rejection::Silent) => &silent,
+                Some(Rejection::TooShort) => &too_short,
+                None if result.is_ok() => &processed,
+                None => &failed,
+            };
+            count.fetch_add(1, Ordering::Relaxed);
+            let record = match result {
+                Ok(analysis) => {
+                    if analysis.capped_start || analysis.capped_end {
+                        capped
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push(job.rel_path.clone());
+                    }
+                    if let Some(frames) = &analysis.segments {
+                        segments.fetch_add(frames.len(), Ordering::Relaxed);
+                        if let Some(manifest) = &manifest {
+                            let mut manifest = manifest.lock().unwrap_or_else(|e| e.into_inner());
+                            for (i, frames) in frames.iter().enumerate() {
+                                let entry = ManifestEntry {
+                                    source: job.rel_path.display().to_string(),
+                                    segment: segment_path(&job.rel_path, i + 1)
+                                        .display()
+                                        .to_string(),
+                                    index: i + 1,
+                                    sample_rate: analysis.sample_rate,
+                                    frames: frames.clone(),
+                                };
+                                if let Err(e) = manifest.write(&entry) {
+                                    eprintln!(
+                                        "Error writing manifest for {}: {:#}",
+                                        job.rel_path.display(),
+                                        e
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Record::from_analysis(&job.rel_path, &analysis)
+                }
+                Err(e) => {
+                    eprintln!("Error processing {}: {}", job.input_path.display(), e);
+                    Record::from_error(&job.rel_path, &e)
+                }
+            };
+            if let Some(report) = &report {
+                let mut report = report.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = report.write(&record) {
+                    eprintln!(
+                        "Error writing report for {}: {:#}",
+                        job.rel_path.display(),
+                        e
+                    );
+                }
+            }
+        },
+    )?;
+
+    if let Some(report) = report {
+        report
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .finish()?;
+    }
+    if let Some(manifest) = manifest {
+        manifest
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .finish()?;
     }
 
-    println!("Processed {} WAV files.", processed);
+    let verb = if args.dry_run {
+        "Analyzed"
+    } else {
+        "Processed"
+    };
+    println!("{} {} WAV files.", verb, processed.into_inner());
+    if args.split {
+        println!(
+            "{} {} segments.",
+            if args.dry_run { "Found" } else { "Wrote" },
+            segments.into_inner()
+        );
+    }
+    let failed = failed.into_inner();
+    if failed > 0 {
+        println!("Failed to process {} WAV files.", failed);
+    }
+    let policy = args
+        .on_silent
+        .to_possible_value()
+        .map(|v| v.get_name().to_string());
+    let policy = policy.unwrap_or_default();
+    let silent = silent.into_inner();
+    if silent > 0 {
+        println!(
+            "Found {} all-silent WAV files (--on-silent {}).",
+            silent, policy
+        );
+    }
+    let too_short = too_short.into_inner();
+    if too_short > 0 {
+        println!(
+            "Found {} WAV files shorter than --min-output-ms (--on-silent {}).",
+            too_short, policy
+        );
+    }
+    let mut capped = capped.into_inner().unwrap_or_else(|e| e.into_inner());
+    if !capped.is_empty() {
+        capped.sort();
+        println!(
+            "Capped the trim of {} WAV files at the maximum; review:",
+            capped.len()
+        );
+        for path in capped {
+            println!("  {}", path.display());
+        }
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+    use source::fixtures::{MONO, spec, tone};
+    use std::io::Read;
+
+    /// Creates an empty scratch directory unique to this test process and `name`.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("wav-files-trim-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn options() -> TrimOptions {
+        TrimOptions {
+            threshold_db: -50.0,
+            start_threshold_db: None,
+            end_threshold_db: None,
+            trim_start: true,
+            trim_end: true,
+            release_db: None,
+            threshold_relative_db: None,
+            relative_to: ThresholdReference::Peak,
+            adaptive: None,
+            policy: ChannelPolicy::Max,
+            detector: DetectorKind::Rms,
+            window_ms: 50.0,
+            hop_ms: 10.0,
+            refine_ms: 1.0,
+            min_sound_ms: 0.0,
+            min_sound_fraction: 1.0,
+            pad_start_ms: 0.0,
+            pad_end_ms: 0.0,
+            max_trim_start_ms: None,
+            max_trim_end_ms: None,
+            snap_radius_ms: 0.0,
+            fade_in_ms: 0.0,
+            fade_out_ms: 0.0,
+            fade_curve: FadeCurve::Linear,
+            strip_metadata: false,
+            marker_policy: MarkerPolicy::Drop,
+            on_silent: SilentPolicy::Empty,
+            min_output_ms: 0.0,
+            max_pause_ms: None,
+            pause_target_ms: 0.0,
+            pause_crossfade_ms: 10.0,
+            split: false,
+            min_gap_ms: 300.0,
+            min_segment_ms: 0.0,
+            max_segment_ms: None,
+        }
+    }
+
+    /// Writes `samples` to a 16 kHz mono 16-bit file.
+    fn write_mono(path: &Path, samples: &[i16]) {
+        let mut writer = WavWriter::create(path, MONO).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    /// Writes a 16 kHz mono 16-bit file with 100 ms bursts of signal between silences of the
+    /// given lengths in milliseconds, and returns the samples of one burst.
+    fn write_bursts(path: &Path, silences_ms: &[usize]) -> Vec<i16> {
+        let signal: Vec<i16> = (0..1600).map(|i| ((i % 40) - 20) * 500).collect();
+        let mut samples = Vec::new();
+        for (i, &ms) in silences_ms.iter().enumerate() {
+            if i > 0 {
+                samples.extend(&signal);
+            }
+            samples.extend(vec![0i16; ms * 16]);
+        }
+        write_mono(path, &samples);
+        signal
+    }
+
+    /// Writes a 16 kHz mono 16-bit file with 100 ms of silence around 100 ms of signal and
+    /// returns the signal samples.
+    fn write_padded_signal(path: &Path) -> Vec<i16> {
+        write_bursts(path, &[100, 100])
+    }
+
+    /// Appends extra chunks to the end of an existing WAV file, fixing up the RIFF size.
+    fn append_chunks(path: &Path, chunks: &[riff::Chunk]) {
+        let mut input = File::open(path).unwrap();
+        let mut layout = WavLayout::read(&mut input).unwrap();
+        layout.chunks.extend_from_slice(chunks);
+        input.seek(SeekFrom::Start(layout.data_offset)).unwrap();
+        let mut audio = Vec::new();
+        input.read_to_end(&mut audio).unwrap();
+        let mut bytes = Vec::new();
+        layout
+            .write(&mut bytes, &mut &audio[..], layout.data_len)
+            .unwrap();
+        fs::write(path, bytes).unwrap();
+    }
 
     #[test]
-    fn test_rms_silence() {
-        let chunk = vec![0i16; 10];
-        assert_eq!(rms(&chunk), 0.0);
+    fn test_trim_wav_preserves_format() {
+        let dir = temp_dir("preserves_format");
+        let input = dir.join("in24.wav");
+        let output = dir.join("out24.wav");
+
+        let wav_spec = WavSpec {
+            sample_rate: 8_000,
+            ..spec(2, 24, SampleFormat::Int)
+        };
+        let signal: Vec<i32> = (0..800).map(|i| (i % 7 - 3) * 300_000).collect();
+        let mut writer = WavWriter::create(&input, wav_spec).unwrap();
+        for &s in [0i32; 800].iter().chain(&signal).chain(&[0i32; 800]) {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        trim_wav(&input, &output, &options()).unwrap();
+
+        let mut reader = WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec(), wav_spec);
+        let trimmed: Vec<i32> = reader.samples::<i32>().map(|s| s.unwrap()).collect();
+        assert_eq!(trimmed, signal);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_rms_full_scale() {
-        let chunk = vec![32767i16; 10];
-        let rms_val = rms(&chunk);
-        assert!((rms_val - 32767.0).abs() < 1e-6);
+    fn test_trim_wav_copies_and_strips_metadata() {
+        let dir = temp_dir("metadata");
+        let input = dir.join("in.wav");
+        let signal = write_padded_signal(&input);
+        let extra = [
+            riff::Chunk {
+                id: *b"bext",
+                data: vec![7; 602],
+            },
+            riff::Chunk {
+                id: *b"iXML",
+                data: b"<BWFXML/>".to_vec(),
+            },
+            riff::Chunk {
+                id: *b"LIST",
+                data: b"INFOICMT\x03\x00\x00\x00hi\x00".to_vec(),
+            },
+        ];
+        append_chunks(&input, &extra);
+
+        let kept = dir.join("kept.wav");
+        trim_wav(&input, &kept, &options()).unwrap();
+        let layout = WavLayout::read(&mut File::open(&kept).unwrap()).unwrap();
+        // bext is carried over but its TimeReference moves with the trimmed start.
+        assert_eq!(&layout.chunks[1].id, b"bext");
+        assert_eq!(&layout.chunks[2..], &extra[1..]);
+        let samples: Vec<i16> = WavReader::open(&kept)
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples, signal);
+
+        let stripped = dir.join("stripped.wav");
+        let strip = TrimOptions {
+            strip_metadata: true,
+            ..options()
+        };
+        trim_wav(&input, &stripped, &strip).unwrap();
+        let layout = WavLayout::read(&mut File::open(&stripped).unwrap()).unwrap();
+        assert_eq!(layout.chunks.len(), 1);
+        assert_eq!(&layout.chunks[0].id, b"fmt ");
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_trim_all_silence() {
-        let samples = vec![0i16; 1000];
-        let threshold_db = -50.0;
-        let window_size = 100;
-        let trimmed = trim_samples(&samples, threshold_db, window_size).unwrap();
-        assert_eq!(trimmed.len(), 0);
+    fn test_short_window_takes_default_hop_no_longer_than_itself() {
+        let parse = |extra: &[&str]| {
+            let args = Args::parse_from(["wav-files-trim", "in", "out"].iter().chain(extra));
+            TrimOptions::from(&args)
+        };
+        assert_eq!(parse(&[]).hop_ms, 10.0);
+        assert_eq!(parse(&["--hop-ms", "20"]).hop_ms, 20.0);
+        let short = parse(&["--window-ms", "5"]);
+        assert_eq!(short.hop_ms, 5.0);
+
+        let dir = temp_dir("short_window");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+        let options = TrimOptions {
+            window_ms: short.window_ms,
+            hop_ms: short.hop_ms,
+            ..options()
+        };
+        assert_eq!(analyze_wav(&input, &options).unwrap().keep, 1600..3200);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_trim_leading_trailing_silence() {
-        let silence_len = 800;
-        let signal = vec![1000i16; 400]; // Above threshold RMS.
-        let samples = vec![0i16; silence_len]
-            .into_iter()
-            .chain(signal.clone())
-            .chain(vec![0i16; silence_len])
-            .collect::<Vec<_>>();
-        let threshold_db = -40.0; // Threshold such that RMS(1000 over 400) > threshold.
-        let window_size = 200;
-        let trimmed = trim_samples(&samples, threshold_db, window_size).unwrap();
-        assert_eq!(trimmed.len(), signal.len());
-        assert_eq!(trimmed, signal);
+    fn test_analyze_wav_reports_cut_points_without_writing() {
+        let dir = temp_dir("analyze");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+
+        let analysis = analyze_wav(&input, &options()).unwrap();
+        assert_eq!(
+            analysis,
+            Analysis {
+                sample_rate: 16_000,
+                channels: 1,
+                total_frames: 4800,
+                keep: 1600..3200,
+                output: Some(Splice::new(1600..3200)),
+                segments: None,
+                rejection: None,
+                padded: 1600..3200,
+                detected: 1600..3200,
+                coarse: 1600..3200,
+                threshold_db: -50.0,
+                start_threshold_db: Some(-50.0),
+                end_threshold_db: Some(-50.0),
+                release_db: None,
+                noise_floor_db: None,
+                reference_db: None,
+                capped_start: false,
+                capped_end: false,
+            }
+        );
+        assert_eq!(
+            analysis.to_string(),
+            "duration 0.300s (4800 samples), start 1600 (0.100s), end 3200 (0.200s), \
+             trimmed duration 0.100s, coarse start 1600, coarse end 3200"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_padding_extends_keep_within_file() {
+        let dir = temp_dir("padding");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+
+        let padded = TrimOptions {
+            pad_start_ms: 50.0,
+            pad_end_ms: 200.0,
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &padded).unwrap();
+        assert_eq!(analysis.detected, 1600..3200);
+        assert_eq!(analysis.keep, 800..4800);
+        assert_eq!(analysis.padding(), (800, 1600));
+        assert!(analysis.to_string().ends_with(
+            ", detected start 1600, detected end 3200, padded 0.050s before and 0.100s after"
+        ));
+        let record = Record::from_analysis(Path::new("in.wav"), &analysis);
+        assert_eq!(record.keep, Some(800..4800));
+        assert_eq!(record.detected, Some(1600..3200));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_single_sided_trim_keeps_other_edge() {
+        let dir = temp_dir("single-sided");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+
+        let only_start = TrimOptions {
+            trim_end: false,
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &only_start).unwrap();
+        assert_eq!(analysis.keep, 1600..4800);
+        assert_eq!(analysis.end_threshold_db, None);
+        assert!(analysis.to_string().ends_with(", end not trimmed"));
+
+        let only_end = TrimOptions {
+            trim_start: false,
+            end_threshold_db: Some(-20.0),
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &only_end).unwrap();
+        assert_eq!(analysis.keep.start, 0);
+        assert_eq!(analysis.end_threshold_db, Some(-20.0));
+        assert!(
+            analysis
+                .to_string()
+                .ends_with(", start not trimmed, end threshold -20.0 dBFS")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_max_trim_limits_cut_and_is_flagged() {
+        let dir = temp_dir("max-trim");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+
+        let limited = TrimOptions {
+            max_trim_start_ms: Some(25.0),
+            max_trim_end_ms: Some(200.0),
+            snap_radius_ms: 5.0,
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &limited).unwrap();
+        assert_eq!(analysis.detected, 400..3200);
+        assert!(analysis.keep.start <= 400);
+        assert!(analysis.capped_start && !analysis.capped_end);
+        assert!(
+            analysis
+                .to_string()
+                .contains(", start capped at maximum trim")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_adaptive_threshold_follows_noise_floor() {
+        // Signal at -14 dBFS over a noise bed at about -34 dBFS, which the fixed -50 dBFS
+        // threshold would keep in full.
+        let dir = temp_dir("adaptive");
+        let input = dir.join("in.wav");
+        let mut writer = WavWriter::create(&input, MONO).unwrap();
+        for i in 0..4800 {
+            let noise = if i % 2 == 0 { 650 } else { -650 };
+            let signal = if (1600..3200).contains(&i) { 6500 } else { 0 };
+            writer.write_sample((noise + signal) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        assert_eq!(analyze_wav(&input, &options()).unwrap().keep, 0..4800);
+        let adaptive = TrimOptions {
+            adaptive: Some(AdaptiveThreshold {
+                percentile: 10.0,
+                margin_db: 10.0,
+                min_db: None,
+                max_db: None,
+            }),
+            refine_ms: 0.0,
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &adaptive).unwrap();
+        let floor = analysis.noise_floor_db.unwrap();
+        assert!((floor - 20.0 * (650.0f64 / 32768.0).log10()).abs() < 0.1);
+        assert_eq!(analysis.threshold_db, floor + 10.0);
+        assert_eq!(analysis.detected, 1600..3200);
+
+        let clamped = TrimOptions {
+            adaptive: Some(AdaptiveThreshold {
+                max_db: Some(-40.0),
+                ..adaptive.adaptive.unwrap()
+            }),
+            ..options()
+        };
+        let analysis = analyze_wav(&input, &clamped).unwrap();
+        assert_eq!(analysis.threshold_db, -40.0);
+        assert_eq!(analysis.keep, 0..4800);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_trim_no_silence() {
-        let samples = vec![1000i16; 1000];
-        let threshold_db = -60.0;
-        let window_size = 100;
-        let trimmed = trim_samples(&samples, threshold_db, window_size).unwrap();
-        assert_eq!(trimmed.len(), samples.len());
+    fn test_relative_threshold_tracks_recording_gain() {
+        // The same take recorded 30 dB apart trims identically with a relative threshold.
+        let dir = temp_dir("relative");
+        let relative = TrimOptions {
+            threshold_relative_db: Some(-30.0),
+            refine_ms: 0.0,
+            ..options()
+        };
+        for (name, gain) in [("loud.wav", 10_000.0), ("quiet.wav", 10_000.0 / 31.62)] {
+            let input = dir.join(name);
+            let mut writer = WavWriter::create(&input, MONO).unwrap();
+            for i in 0..4800 {
+                // A tail at -40 dB relative to the peak sits below the relative threshold.
+                let level = match i {
+                    1600..3200 => 1.0,
+                    3200..4000 => 0.01,
+                    _ => 0.0,
+                };
+                let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+                writer.write_sample((gain * level * sign) as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+
+            let analysis = analyze_wav(&input, &relative).unwrap();
+            let reference = analysis.reference_db.unwrap();
+            assert!((reference - 20.0 * (gain / 32768.0f64).log10()).abs() < 0.01);
+            assert_eq!(analysis.threshold_db, reference - 30.0);
+            assert_eq!(analysis.keep, 1600..3200, "{name}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spectral_detector_trims_hum() {
+        // 100 ms of a 1 kHz tone between stretches of 50 Hz hum at the same level.
+        let dir = temp_dir("detector");
+        let input = dir.join("in.wav");
+        let mut samples = tone(50.0, 8000);
+        samples[3200..4800].copy_from_slice(&tone(1000.0, 8000)[3200..4800]);
+        write_mono(&input, &samples);
+
+        let hum = TrimOptions {
+            threshold_db: -40.0,
+            ..options()
+        };
+        assert_eq!(analyze_wav(&input, &hum).unwrap().keep, 0..8000);
+        let spectral = TrimOptions {
+            detector: DetectorKind::Spectral,
+            ..hum
+        };
+        let keep = analyze_wav(&input, &spectral).unwrap().keep;
+        assert!((3200..3240).contains(&keep.start), "{keep:?}");
+        assert!((4760..4840).contains(&keep.end), "{keep:?}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trim_wav_fades_edges() {
+        let dir = temp_dir("fade");
+        let input = dir.join("in.wav");
+        let output = dir.join("out.wav");
+        let signal = write_padded_signal(&input);
+
+        let faded = TrimOptions {
+            fade_in_ms: 1.0,
+            fade_out_ms: 2.0,
+            fade_curve: FadeCurve::RaisedCosine,
+            ..options()
+        };
+        trim_wav(&input, &output, &faded).unwrap();
+        let samples: Vec<i16> = WavReader::open(&output)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(samples.len(), signal.len());
+        // 1 ms and 2 ms at 16 kHz are 16 and 32 frames.
+        assert_eq!(samples[0], 0);
+        assert_eq!(*samples.last().unwrap(), 0);
+        assert!(samples[1].abs() < signal[1].abs());
+        assert_eq!(
+            &samples[16..signal.len() - 32],
+            &signal[16..signal.len() - 32]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trim_wav_applies_silent_policy_to_short_output() {
+        let dir = temp_dir("silent-policy");
+        let input = dir.join("in.wav");
+        let output = dir.join("out.wav");
+        write_padded_signal(&input);
+        // The 100 ms signal is shorter than the minimum.
+        let policy = |on_silent| TrimOptions {
+            min_output_ms: 150.0,
+            on_silent,
+            fade_in_ms: 10.0,
+            ..options()
+        };
+
+        let outcome = trim_wav(&input, &output, &policy(SilentPolicy::Skip)).unwrap();
+        assert_eq!(outcome.analysis.rejection, Some(Rejection::TooShort));
+        assert!(!output.exists());
+
+        trim_wav(&input, &output, &policy(SilentPolicy::Copy)).unwrap();
+        let original: Vec<i16> = WavReader::open(&input)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        let copied: Vec<i16> = WavReader::open(&output)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(copied, original);
+
+        let outcome = trim_wav(&input, &output, &policy(SilentPolicy::Empty)).unwrap();
+        assert!(
+            outcome
+                .analysis
+                .to_string()
+                .ends_with(", shorter than the minimum output length, written empty")
+        );
+        assert_eq!(WavReader::open(&output).unwrap().len(), 0);
+
+        let error = trim_wav(&input, &output, &policy(SilentPolicy::Error)).unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&Rejection::TooShort));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trim_wav_shortens_internal_pauses() {
+        let dir = temp_dir("pauses");
+        let input = dir.join("in.wav");
+        let output = dir.join("out.wav");
+        // Two bursts 500 ms apart, with 100 ms of silence at either edge.
+        let signal = write_bursts(&input, &[100, 500, 100]);
+
+        let pauses = TrimOptions {
+            max_pause_ms: Some(200.0),
+            pause_target_ms: 100.0,
+            ..options()
+        };
+        let outcome = trim_wav(&input, &output, &pauses).unwrap();
+        assert_eq!(outcome.analysis.keep, 1600..12_800);
+        assert!(
+            outcome
+                .analysis
+                .to_string()
+                .ends_with(", shortened 1 pauses by 0.400s")
+        );
+        let samples: Vec<i16> = WavReader::open(&output)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(samples.len(), 4800);
+        assert_eq!(&samples[..1600], &signal[..]);
+        assert!(samples[1600..3200].iter().all(|&s| s == 0));
+        assert_eq!(&samples[3200..], &signal[..]);
+
+        // Edges stay untouched when only pauses are shortened.
+        let pauses_only = TrimOptions {
+            trim_start: false,
+            trim_end: false,
+            ..pauses
+        };
+        let analysis = analyze_wav(&input, &pauses_only).unwrap();
+        assert_eq!(analysis.output.unwrap().frames(), 14_400 - 6400);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_wav_writes_nothing_for_rejected_files() {
+        let dir = temp_dir("split_rejected");
+        let input = dir.join("silent.wav");
+        write_mono(&input, &[0i16; 4800]);
+        for on_silent in [SilentPolicy::Empty, SilentPolicy::Copy, SilentPolicy::Skip] {
+            let split = TrimOptions {
+                split: true,
+                on_silent,
+                ..options()
+            };
+            let outcome = split_wav(&input, &dir.join("out.wav"), &split).unwrap();
+            assert_eq!(outcome.analysis.rejection, Some(Rejection::Silent));
+            assert_eq!(outcome.analysis.segments, Some(Vec::new()));
+            assert!(outcome.metadata.is_empty());
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_wav_writes_numbered_segments() {
+        let dir = temp_dir("split");
+        let input = dir.join("take.wav");
+        // Three bursts separated by 500 ms and 100 ms of silence.
+        let signal = write_bursts(&input, &[100, 500, 100, 100]);
+
+        let split = TrimOptions {
+            split: true,
+            pad_start_ms: 50.0,
+            pad_end_ms: 50.0,
+            ..options()
+        };
+        let outcome = split_wav(&input, &dir.join("out.wav"), &split).unwrap();
+        let analysis = outcome.analysis;
+        assert_eq!(analysis.segments, Some(vec![800..4000, 10_400..16_800]));
+        assert_eq!(analysis.output_frames(), 9600);
+        assert!(analysis.to_string().contains(", split into 2 segments"));
+        assert_eq!(outcome.metadata.len(), 2);
+
+        let read = |name: &str| -> Vec<i16> {
+            WavReader::open(dir.join(name))
+                .unwrap()
+                .into_samples()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let first = read("out_0001.wav");
+        assert_eq!(first.len(), 3200);
+        assert_eq!(&first[800..2400], &signal[..]);
+        assert_eq!(read("out_0002.wav").len(), 6400);
+        assert!(!dir.join("out.wav").exists());
+        assert_eq!(
+            segment_path(Path::new("a/b.w64"), 12),
+            Path::new("a/b_0012.wav")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wave64_input_sharing_output_with_wav_is_detected() {
+        let dir = temp_dir("collision");
+        fs::write(dir.join("take.w64"), b"").unwrap();
+        fs::write(dir.join("solo.w64"), b"").unwrap();
+        fs::write(dir.join("take.wav"), b"").unwrap();
+        assert!(shares_output_with_wav(&dir.join("take.w64")));
+        assert!(!shares_output_with_wav(&dir.join("take.wav")));
+        assert!(!shares_output_with_wav(&dir.join("solo.w64")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trim_wav_shifts_cue_points() {
+        let dir = temp_dir("cue");
+        let input = dir.join("in.wav");
+        write_padded_signal(&input);
+        // One cue point in the leading silence and one at the start of the signal.
+        let mut cue = 2u32.to_le_bytes().to_vec();
+        for (id, offset) in [(1u32, 10u32), (2, 1600)] {
+            for field in [id, offset, u32::from_le_bytes(*b"data"), 0, 0, offset] {
+                cue.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        append_chunks(
+            &input,
+            &[riff::Chunk {
+                id: *b"cue ",
+                data: cue,
+            }],
+        );
+
+        let output = dir.join("out.wav");
+        let report = trim_wav(&input, &output, &options()).unwrap().metadata;
+        assert_eq!((report.cues_shifted, report.cues_dropped), (1, 1));
+        let layout = WavLayout::read(&mut File::open(&output).unwrap()).unwrap();
+        let cue = &layout.chunk(b"cue ").unwrap().data;
+        assert_eq!(cue.len(), 4 + 24);
+        assert_eq!(&cue[4..8], &2u32.to_le_bytes());
+        assert_eq!(&cue[24..28], &0u32.to_le_bytes());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,387 @@
+//! Adjustment of time-anchored metadata (`bext`, `cue `, `LIST/adtl`, `smpl`) after trimming.
+
+use crate::riff::{Chunk, WavLayout};
+use crate::splice::Splice;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+/// Byte offset of the 64-bit TimeReference field inside a `bext` chunk.
+const BEXT_TIME_REFERENCE: usize = 338;
+/// Size of one cue point record in a `cue ` chunk.
+const CUE_POINT_LEN: usize = 24;
+/// Size of the fixed `smpl` chunk header that precedes the loop records.
+const SMPL_HEADER_LEN: usize = 36;
+/// Size of one loop record in a `smpl` chunk.
+const SMPL_LOOP_LEN: usize = 24;
+
+/// What to do with a marker or loop point that falls inside a removed region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MarkerPolicy {
+    /// Remove the marker (and any labels attached to it).
+    #[default]
+    Drop,
+    /// Move the marker to the nearest edge of the kept audio.
+    Clamp,
+}
+
+/// Counts of the metadata positions changed while trimming a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataReport {
+    /// Frames added to the `bext` TimeReference, if the file has one.
+    pub time_reference_shift: Option<u64>,
+    /// Cue points moved to account for removed leading audio.
+    pub cues_shifted: usize,
+    /// Cue points that fell in a removed region and were clamped to an edge.
+    pub cues_clamped: usize,
+    /// Cue points that fell in a removed region and were removed.
+    pub cues_dropped: usize,
+    /// Sampler loops moved to account for removed leading audio.
+    pub loops_shifted: usize,
+    /// Sampler loops that extended into a removed region and were clamped.
+    pub loops_clamped: usize,
+    /// Sampler loops that extended into a removed region and were removed.
+    pub loops_dropped: usize,
+}
+
+impl MetadataReport {
+    /// Returns true if no metadata position was touched.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for MetadataReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(shift) = self.time_reference_shift {
+            parts.push(format!("bext TimeReference +{shift}"));
+        }
+        for (count, what) in [
+            (self.cues_shifted, "cue points shifted"),
+            (self.cues_clamped, "cue points clamped"),
+            (self.cues_dropped, "cue points dropped"),
+            (self.loops_shifted, "loops shifted"),
+            (self.loops_clamped, "loops clamped"),
+            (self.loops_dropped, "loops dropped"),
+        ] {
+            if count > 0 {
+                parts.push(format!("{count} {what}"));
+            }
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Rewrites time-anchored metadata in `layout` so it stays correct once only the frames in `keep`
+/// remain.
+///
+/// The `bext` TimeReference is advanced by the first kept frame. Cue points and sampler loops
+/// are moved to their place in the output, closing up any shortened pauses; those in removed
+/// audio are dropped or clamped according to `policy`, and `LIST/adtl` labels of dropped cue
+/// points are removed with them.
+pub fn adjust_positions(
+    layout: &mut WavLayout,
+    keep: &Splice,
+    policy: MarkerPolicy,
+) -> MetadataReport {
+    let mut report = MetadataReport::default();
+
+    if keep.start() > 0
+        && let Some(bext) = layout.chunk_mut(b"bext")
+        && bext.data.len() >= BEXT_TIME_REFERENCE + 8
+    {
+        let field = &mut bext.data[BEXT_TIME_REFERENCE..BEXT_TIME_REFERENCE + 8];
+        let time_reference = u64::from_le_bytes(field.try_into().unwrap());
+        field.copy_from_slice(&time_reference.wrapping_add(keep.start()).to_le_bytes());
+        report.time_reference_shift = Some(keep.start());
+    }
+
+    let mut cues = HashMap::new();
+    if let Some(cue) = layout.chunk_mut(b"cue ") {
+        adjust_cue(cue, keep, policy, &mut report, &mut cues);
+    }
+    for list in layout.chunks.iter_mut().filter(|c| &c.id == b"LIST") {
+        if list.data.starts_with(b"adtl") {
+            adjust_adtl(list, keep, &cues);
+        }
+    }
+    if let Some(smpl) = layout.chunk_mut(b"smpl") {
+        adjust_smpl(smpl, keep, policy, &mut report);
+    }
+
+    report
+}
+
+/// Original and new sample offset of each cue point by ID, or `None` if the cue point was
+/// dropped.
+type CueOffsets = HashMap<u32, Option<Range<u64>>>;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u64) {
+    let value = u32::try_from(value).unwrap_or(u32::MAX);
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn adjust_cue(
+    cue: &mut Chunk,
+    keep: &Splice,
+    policy: MarkerPolicy,
+    report: &mut MetadataReport,
+    cues: &mut CueOffsets,
+) {
+    if cue.data.len() < 4 {
+        return;
+    }
+    let count = (read_u32(&cue.data, 0) as usize).min((cue.data.len() - 4) / CUE_POINT_LEN);
+    let mut points = Vec::with_capacity(count * CUE_POINT_LEN);
+    for record in cue.data[4..4 + count * CUE_POINT_LEN].chunks_exact(CUE_POINT_LEN) {
+        let mut record = record.to_vec();
+        let id = read_u32(&record, 0);
+        let position = u64::from(read_u32(&record, 4));
+        let offset = u64::from(read_u32(&record, 20));
+        let new_offset = match (keep.map(offset), policy) {
+            (Some(mapped), _) => {
+                if mapped != offset {
+                    report.cues_shifted += 1;
+                }
+                mapped
+            }
+            (None, MarkerPolicy::Clamp) => {
+                report.cues_clamped += 1;
+                keep.clamp(offset)
+            }
+            (None, MarkerPolicy::Drop) => {
+                report.cues_dropped += 1;
+                cues.insert(id, None);
+                continue;
+            }
+        };
+        cues.insert(id, Some(offset..new_offset));
+        // dwPosition normally equals dwSampleOffset; keep any difference between them intact.
+        write_u32(
+            &mut record,
+            4,
+            (position + new_offset).saturating_sub(offset),
+        );
+        write_u32(&mut record, 20, new_offset);
+        points.extend_from_slice(&record);
+    }
+
+    let mut data = ((points.len() / CUE_POINT_LEN) as u32)
+        .to_le_bytes()
+        .to_vec();
+    data.extend_from_slice(&points);
+    cue.data = data;
+}
+
+/// Removes labels of dropped cue points from a `LIST/adtl` chunk and shortens `ltxt` regions by
+/// the audio removed from them.
+fn adjust_adtl(list: &mut Chunk, keep: &Splice, cues: &CueOffsets) {
+    let mut data = b"adtl".to_vec();
+    let mut pos = 4;
+    while pos + 8 <= list.data.len() {
+        let id = &list.data[pos..pos + 4];
+        let size = (read_u32(&list.data, pos + 4) as usize).min(list.data.len() - pos - 8);
+        let mut body = list.data[pos + 8..pos + 8 + size].to_vec();
+        let next = pos + 8 + size + (size & 1);
+
+        let cue = if body.len() >= 4 {
+            cues.get(&read_u32(&body, 0)).cloned()
+        } else {
+            None
+        };
+        if cue == Some(None) {
+            pos = next;
+            continue;
+        }
+        if id == b"ltxt"
+            && body.len() >= 8
+            && let Some(Some(offsets)) = &cue
+        {
+            // Regions start at their (already moved) cue point and end where their last frame
+            // lands in the output.
+            let length = u64::from(read_u32(&body, 4));
+            let end = keep.clamp(offsets.start + length);
+            write_u32(&mut body, 4, end.saturating_sub(offsets.end));
+        }
+
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend_from_slice(&body);
+        if body.len() % 2 == 1 {
+            data.push(0);
+        }
+        pos = next;
+    }
+    list.data = data;
+}
+
+fn adjust_smpl(smpl: &mut Chunk, keep: &Splice, policy: MarkerPolicy, report: &mut MetadataReport) {
+    if smpl.data.len() < SMPL_HEADER_LEN {
+        return;
+    }
+    let available = (smpl.data.len() - SMPL_HEADER_LEN) / SMPL_LOOP_LEN;
+    let count = (read_u32(&smpl.data, 28) as usize).min(available);
+    let loops_end = SMPL_HEADER_LEN + count * SMPL_LOOP_LEN;
+
+    let mut loops = Vec::with_capacity(count * SMPL_LOOP_LEN);
+    for record in smpl.data[SMPL_HEADER_LEN..loops_end].chunks_exact(SMPL_LOOP_LEN) {
+        let mut record = record.to_vec();
+        let start = u64::from(read_u32(&record, 8));
+        let end = u64::from(read_u32(&record, 12));
+        let (new_start, new_end) = match (keep.map(start), keep.map(end)) {
+            (Some(s), Some(e)) => {
+                if s != start {
+                    report.loops_shifted += 1;
+                }
+                (s, e)
+            }
+            _ if policy == MarkerPolicy::Clamp && keep.clamp(start) < keep.clamp(end) => {
+                report.loops_clamped += 1;
+                (keep.clamp(start), keep.clamp(end))
+            }
+            _ => {
+                report.loops_dropped += 1;
+                continue;
+            }
+        };
+        write_u32(&mut record, 8, new_start);
+        write_u32(&mut record, 12, new_end);
+        loops.extend_from_slice(&record);
+    }
+
+    let mut data = smpl.data[..SMPL_HEADER_LEN].to_vec();
+    write_u32(&mut data, 28, (loops.len() / SMPL_LOOP_LEN) as u64);
+    data.extend_from_slice(&loops);
+    data.extend_from_slice(&smpl.data[loops_end..]);
+    smpl.data = data;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], data: Vec<u8>) -> Chunk {
+        Chunk { id: *id, data }
+    }
+
+    fn layout(chunks: Vec<Chunk>) -> WavLayout {
+        WavLayout {
+            data_index: chunks.len(),
+            chunks,
+            data_offset: 0,
+            data_len: 0,
+        }
+    }
+
+    fn cue_chunk(points: &[(u32, u32)]) -> Chunk {
+        let mut data = (points.len() as u32).to_le_bytes().to_vec();
+        for &(id, offset) in points {
+            for field in [id, offset, u32::from_le_bytes(*b"data"), 0, 0, offset] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        chunk(b"cue ", data)
+    }
+
+    fn cue_offsets(layout: &WavLayout) -> Vec<(u32, u32, u32)> {
+        let data = &layout.chunk(b"cue ").unwrap().data;
+        data[4..]
+            .chunks_exact(CUE_POINT_LEN)
+            .map(|r| (read_u32(r, 0), read_u32(r, 4), read_u32(r, 20)))
+            .collect()
+    }
+
+    #[test]
+    fn test_bext_time_reference_advances_by_start_trim() {
+        let mut bext = vec![0u8; 602];
+        bext[BEXT_TIME_REFERENCE..BEXT_TIME_REFERENCE + 8]
+            .copy_from_slice(&1_000_000u64.to_le_bytes());
+        let mut layout = layout(vec![chunk(b"bext", bext)]);
+        let report = adjust_positions(&mut layout, &Splice::new(480..48_000), MarkerPolicy::Drop);
+        let data = &layout.chunk(b"bext").unwrap().data;
+        let time_reference = u64::from_le_bytes(
+            data[BEXT_TIME_REFERENCE..BEXT_TIME_REFERENCE + 8]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(time_reference, 1_000_480);
+        assert_eq!(report.time_reference_shift, Some(480));
+    }
+
+    #[test]
+    fn test_cue_points_shift_and_drop_with_labels() {
+        let mut adtl = b"adtl".to_vec();
+        for (id, text) in [(1u32, b"in\0\0"), (2, b"ok\0\0")] {
+            adtl.extend_from_slice(b"labl");
+            adtl.extend_from_slice(&8u32.to_le_bytes());
+            adtl.extend_from_slice(&id.to_le_bytes());
+            adtl.extend_from_slice(text);
+        }
+        let mut layout = layout(vec![
+            cue_chunk(&[(1, 50), (2, 500), (3, 5_000)]),
+            chunk(b"LIST", adtl),
+        ]);
+        let report = adjust_positions(&mut layout, &Splice::new(100..1_000), MarkerPolicy::Drop);
+
+        assert_eq!(cue_offsets(&layout), vec![(2, 400, 400)]);
+        assert_eq!(report.cues_shifted, 1);
+        assert_eq!(report.cues_dropped, 2);
+        let list = &layout.chunk(b"LIST").unwrap().data;
+        assert_eq!(list.len(), 4 + 16);
+        assert_eq!(read_u32(list, 12), 2);
+    }
+
+    #[test]
+    fn test_cue_points_clamp_to_kept_edges() {
+        let mut layout = layout(vec![cue_chunk(&[(1, 50), (2, 5_000)])]);
+        let report = adjust_positions(&mut layout, &Splice::new(100..1_000), MarkerPolicy::Clamp);
+        assert_eq!(cue_offsets(&layout), vec![(1, 0, 0), (2, 900, 900)]);
+        assert_eq!(report.cues_clamped, 2);
+    }
+
+    #[test]
+    fn test_cue_points_close_up_shortened_pauses() {
+        // Kept segments 100..400 and 900..1_000 join with a 20-frame crossfade at output 280.
+        let splice = Splice::collapse(100..1_000, std::slice::from_ref(&(340..960)), 100, 20);
+        let mut layout = layout(vec![cue_chunk(&[(1, 200), (2, 600), (3, 980)])]);
+        let report = adjust_positions(&mut layout, &splice, MarkerPolicy::Drop);
+        assert_eq!(cue_offsets(&layout), vec![(1, 100, 100), (3, 360, 360)]);
+        assert_eq!((report.cues_shifted, report.cues_dropped), (2, 1));
+    }
+
+    #[test]
+    fn test_smpl_loops_shift_clamp_and_drop() {
+        let mut smpl = vec![0u8; SMPL_HEADER_LEN];
+        smpl[28..32].copy_from_slice(&3u32.to_le_bytes());
+        for (start, end) in [(200u32, 300u32), (50, 400), (10, 20)] {
+            for field in [0, 0, start, end, 0, 0] {
+                smpl.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        let loops = |layout: &WavLayout| {
+            let data = &layout.chunk(b"smpl").unwrap().data;
+            data[SMPL_HEADER_LEN..]
+                .chunks_exact(SMPL_LOOP_LEN)
+                .map(|r| (read_u32(r, 8), read_u32(r, 12)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut dropped = layout(vec![chunk(b"smpl", smpl.clone())]);
+        let report = adjust_positions(&mut dropped, &Splice::new(100..1_000), MarkerPolicy::Drop);
+        assert_eq!(loops(&dropped), vec![(100, 200)]);
+        assert_eq!((report.loops_shifted, report.loops_dropped), (1, 2));
+
+        let mut clamped = layout(vec![chunk(b"smpl", smpl)]);
+        let report = adjust_positions(&mut clamped, &Splice::new(100..1_000), MarkerPolicy::Clamp);
+        assert_eq!(loops(&clamped), vec![(100, 200), (0, 300)]);
+        assert_eq!(report.loops_clamped, 1);
+        assert_eq!(report.loops_dropped, 1);
+        assert_eq!(read_u32(&clamped.chunk(b"smpl").unwrap().data, 28), 2);
+    }
+}
@@ -0,0 +1,103 @@
+//! Fixed-size worker pool fed through a bounded queue.
+
+use anyhow::Result;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Runs `work` on every item passed to the `send` callback of `produce`, using `jobs` worker
+/// threads.
+///
+/// Items travel through a queue holding at most `queue_len` entries, so a producer that is
+/// faster than the workers blocks instead of buffering its whole input. Returns the producer's
+/// result once every queued item has been handled.
+///
+/// # Panics
+///
+/// Propagates a panic from any worker once the remaining workers have finished.
+pub fn run<T, P, W>(jobs: usize, queue_len: usize, produce: P, work: W) -> Result<()>
+where
+    T: Send,
+    P: FnOnce(&mut dyn FnMut(T)) -> Result<()>,
+    W: Fn(T) + Sync,
+{
+    let (tx, rx) = mpsc::sync_channel::<T>(queue_len);
+    // Workers share the receiver; once they are all gone it is dropped and sends fail instead of
+    // blocking forever.
+    let rx = Arc::new(Mutex::new(rx));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            let rx = Arc::clone(&rx);
+            let work = &work;
+            scope.spawn(move || {
+                loop {
+                    let item = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+                    match item {
+                        Ok(item) => work(item),
+                        Err(_) => return,
+                    }
+                }
+            });
+        }
+        drop(rx);
+
+        let result = produce(&mut |item| {
+            // Only fails if every worker has panicked; the panic surfaces when the scope ends.
+            let _ = tx.send(item);
+        });
+        drop(tx);
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_runs_every_item_once() {
+        let sum = AtomicUsize::new(0);
+        let count = AtomicUsize::new(0);
+        run(
+            4,
+            2,
+            |send| {
+                for i in 1..=1000 {
+                    send(i);
+                }
+                Ok(())
+            },
+            |i: usize| {
+                sum.fetch_add(i, Ordering::Relaxed);
+                count.fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .unwrap();
+        assert_eq!(count.into_inner(), 1000);
+        assert_eq!(sum.into_inner(), 500_500);
+    }
+
+    #[test]
+    fn test_returns_producer_error_after_draining() {
+        let count = AtomicUsize::new(0);
+        let result = run(
+            2,
+            1,
+            |send| {
+                send(1);
+                send(2);
+                anyhow::bail!("walk failed")
+            },
+            |_: i32| {
+                count.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(count.into_inner(), 2);
+    }
+}
@@ -0,0 +1,513 @@
+//! Machine-readable per-file report written alongside a run.
+
+use crate::riff::{Malformed, Unsupported};
+use crate::splice::Splice;
+use crate::{Analysis, Rejection};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+
+/// Encoding of the report file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+impl ReportFormat {
+    /// Picks CSV for a `.csv` path and JSON Lines for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ReportFormat::Csv,
+            _ => ReportFormat::Jsonl,
+        }
+    }
+}
+
+/// What happened to a single input file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Silence was removed from at least one end.
+    Trimmed,
+    /// No silence was found, so all audio was kept.
+    Unchanged,
+    /// The whole file is below the threshold.
+    AllSilent,
+    /// The trimmed audio is shorter than the minimum output length.
+    TooShort,
+    /// The file is valid but uses a layout this tool does not handle.
+    Skipped,
+    /// Reading, parsing or writing the file failed.
+    Error,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Trimmed => "trimmed",
+            Status::Unchanged => "unchanged",
+            Status::AllSilent => "all-silent",
+            Status::TooShort => "too-short",
+            Status::Skipped => "skipped",
+            Status::Error => "error",
+        })
+    }
+}
+
+/// One row of the report. Fields that do not apply to a failed file are `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// Path relative to the input directory.
+    pub path: String,
+    /// Outcome for the file.
+    pub status: Status,
+    /// Short machine-friendly error class: `unsupported`, `rejected`, `io` or `invalid`.
+    pub error_category: Option<&'static str>,
+    /// Full error message including its causes.
+    pub error_message: Option<String>,
+    /// Sample rate of the input in Hz.
+    pub sample_rate: Option<u32>,
+    /// Number of interleaved channels.
+    pub channels: Option<u16>,
+    /// Whole frames in the input.
+    pub input_frames: Option<u64>,
+    /// Frames written (or that would be written in a dry run).
+    pub output_frames: Option<u64>,
+    /// Leading silence removed, in milliseconds.
+    pub leading_ms: Option<f64>,
+    /// Trailing silence removed, in milliseconds.
+    pub trailing_ms: Option<f64>,
+    /// Pre-roll added before the detected start, in milliseconds.
+    pub pad_start_ms: Option<f64>,
+    /// Post-roll added after the detected end, in milliseconds.
+    pub pad_end_ms: Option<f64>,
+    /// Internal pauses that were shortened.
+    pub pauses: Option<usize>,
+    /// Total time removed from shortened pauses, in milliseconds.
+    pub pause_removed_ms: Option<f64>,
+    /// Segment files the kept audio was split into, when splitting.
+    pub segments: Option<usize>,
+    /// Detection threshold used for the file, in dBFS.
+    pub threshold_db: Option<f64>,
+    /// Hysteresis release level in dBFS, if used.
+    pub release_db: Option<f64>,
+    /// Estimated noise floor in dBFS when the threshold is adaptive.
+    pub noise_floor_db: Option<f64>,
+    /// Measured peak or loudest-window level in dBFS when the threshold is relative.
+    pub reference_db: Option<f64>,
+    /// Frames the start cut moved when snapped to a zero crossing (positive is later).
+    pub snap_start_frames: Option<i64>,
+    /// Frames the end cut moved when snapped to a zero crossing (positive is later).
+    pub snap_end_frames: Option<i64>,
+    /// The start was trimmed only up to the maximum trim limit.
+    pub capped_start: Option<bool>,
+    /// The end was trimmed only up to the maximum trim limit.
+    pub capped_end: Option<bool>,
+    /// Kept frames in the input, including padding and snapping.
+    pub keep: Option<Range<u64>>,
+    /// Sample-accurate cut points in the input, before padding and snapping.
+    pub detected: Option<Range<u64>>,
+    /// Kept frames before refinement, quantized to the detection hop.
+    pub coarse: Option<Range<u64>>,
+}
+
+const COLUMNS: [&str; 29] = [
+    "path",
+    "status",
+    "error_category",
+    "error_message",
+    "sample_rate",
+    "channels",
+    "input_frames",
+    "output_frames",
+    "leading_ms",
+    "trailing_ms",
+    "pad_start_ms",
+    "pad_end_ms",
+    "pauses",
+    "pause_removed_ms",
+    "segments",
+    "threshold_db",
+    "release_db",
+    "noise_floor_db",
+    "reference_db",
+    "snap_start_frames",
+    "snap_end_frames",
+    "capped_start",
+    "capped_end",
+    "start_frame",
+    "end_frame",
+    "detected_start_frame",
+    "detected_end_frame",
+    "coarse_start_frame",
+    "coarse_end_frame",
+];
+
+impl Record {
+    /// Builds the record for a file that was analyzed (and possibly trimmed).
+    pub fn from_analysis(path: &Path, analysis: &Analysis) -> Self {
+        let keep = &analysis.keep;
+        let status = if keep.is_empty() {
+            Status::AllSilent
+        } else if analysis.rejection == Some(Rejection::TooShort) {
+            Status::TooShort
+        } else if keep.start == 0 && keep.end == analysis.total_frames {
+            Status::Unchanged
+        } else {
+            Status::Trimmed
+        };
+        // An all-silent file has nothing to keep, so everything counts as leading silence.
+        let (leading, trailing) = if keep.is_empty() {
+            (analysis.total_frames, 0)
+        } else {
+            (keep.start, analysis.total_frames - keep.end)
+        };
+        let (pad_start, pad_end) = analysis.padding();
+        let (snap_start, snap_end) = analysis.snap_shift();
+        Self {
+            path: path.display().to_string(),
+            status,
+            error_category: None,
+            error_message: None,
+            sample_rate: Some(analysis.sample_rate),
+            channels: Some(analysis.channels),
+            input_frames: Some(analysis.total_frames),
+            output_frames: Some(analysis.output_frames()),
+            leading_ms: Some(analysis.seconds(leading) * 1000.0),
+            trailing_ms: Some(analysis.seconds(trailing) * 1000.0),
+            pad_start_ms: Some(analysis.seconds(pad_start) * 1000.0),
+            pad_end_ms: Some(analysis.seconds(pad_end) * 1000.0),
+            pauses: Some(analysis.output.as_ref().map_or(0, Splice::joins)),
+            pause_removed_ms: Some(
+                analysis.seconds(analysis.output.as_ref().map_or(0, Splice::removed)) * 1000.0,
+            ),
+            segments: analysis.segments.as_ref().map(Vec::len),
+            threshold_db: Some(analysis.threshold_db),
+            release_db: analysis.release_db,
+            noise_floor_db: analysis.noise_floor_db,
+            reference_db: analysis.reference_db,
+            snap_start_frames: Some(snap_start),
+            snap_end_frames: Some(snap_end),
+            capped_start: Some(analysis.capped_start),
+            capped_end: Some(analysis.capped_end),
+            keep: Some(keep.clone()),
+            detected: Some(analysis.detected.clone()),
+            coarse: Some(analysis.coarse.clone()),
+        }
+    }
+
+    /// Builds the record for a file that could not be processed, classifying `error` by the
+    /// first recognized cause in its chain. Failures to parse the chunk layout are `invalid`
+    /// even when an I/O error such as an early end of file caused them.
+    pub fn from_error(path: &Path, error: &anyhow::Error) -> Self {
+        let rejection = error.chain().find_map(|e| e.downcast_ref::<Rejection>());
+        let (status, category) = if error.chain().any(|e| e.is::<Unsupported>()) {
+            (Status::Skipped, "unsupported")
+        } else if let Some(rejection) = rejection {
+            let status = match rejection {
+                Rejection::Silent => Status::AllSilent,
+                Rejection::TooShort => Status::TooShort,
+            };
+            (status, "rejected")
+        } else if error.downcast_ref::<Malformed>().is_some() {
+            (Status::Error, "invalid")
+        } else if error.chain().any(|e| e.is::<io::Error>()) {
+            (Status::Error, "io")
+        } else {
+            (Status::Error, "invalid")
+        };
+        Self {
+            path: path.display().to_string(),
+            status,
+            error_category: Some(category),
+            error_message: Some(format!("{error:#}")),
+            sample_rate: None,
+            channels: None,
+            input_frames: None,
+            output_frames: None,
+            leading_ms: None,
+            trailing_ms: None,
+            pad_start_ms: None,
+            pad_end_ms: None,
+            pauses: None,
+            pause_removed_ms: None,
+            segments: None,
+            threshold_db: None,
+            release_db: None,
+            noise_floor_db: None,
+            reference_db: None,
+            snap_start_frames: None,
+            snap_end_frames: None,
+            capped_start: None,
+            capped_end: None,
+            keep: None,
+            detected: None,
+            coarse: None,
+        }
+    }
+}
+
+impl Row for Record {
+    const COLUMNS: &'static [&'static str] = &COLUMNS;
+
+    fn values(&self) -> Vec<(bool, Option<String>)> {
+        let num = |v: Option<String>| (false, v);
+        let ms = |v: Option<f64>| num(v.map(|ms| format!("{ms:.3}")));
+        // JSON has no infinities; digital silence measures as -inf dBFS and is left empty.
+        let db = |v: Option<f64>| num(v.filter(|db| db.is_finite()).map(|db| format!("{db:.2}")));
+        vec![
+            (true, Some(self.path.clone())),
+            (true, Some(self.status.to_string())),
+            (true, self.error_category.map(str::to_string)),
+            (true, self.error_message.clone()),
+            num(self.sample_rate.map(|v| v.to_string())),
+            num(self.channels.map(|v| v.to_string())),
+            num(self.input_frames.map(|v| v.to_string())),
+            num(self.output_frames.map(|v| v.to_string())),
+            ms(self.leading_ms),
+            ms(self.trailing_ms),
+            ms(self.pad_start_ms),
+            ms(self.pad_end_ms),
+            num(self.pauses.map(|v| v.to_string())),
+            ms(self.pause_removed_ms),
+            num(self.segments.map(|v| v.to_string())),
+            db(self.threshold_db),
+            db(self.release_db),
+            db(self.noise_floor_db),
+            db(self.reference_db),
+            num(self.snap_start_frames.map(|v| v.to_string())),
+            num(self.snap_end_frames.map(|v| v.to_string())),
+            num(self.capped_start.map(|v| v.to_string())),
+            num(self.capped_end.map(|v| v.to_string())),
+            num(self.keep.as_ref().map(|r| r.start.to_string())),
+            num(self.keep.as_ref().map(|r| r.end.to_string())),
+            num(self.detected.as_ref().map(|r| r.start.to_string())),
+            num(self.detected.as_ref().map(|r| r.end.to_string())),
+            num(self.coarse.as_ref().map(|r| r.start.to_string())),
+            num(self.coarse.as_ref().map(|r| r.end.to_string())),
+        ]
+    }
+}
+
+/// One line of a report file, encoded as JSON Lines or CSV.
+pub trait Row {
+    /// Column names, in order.
+    const COLUMNS: &'static [&'static str];
+
+    /// Returns each column as `(is_string, text)`, with `None` for empty values.
+    fn values(&self) -> Vec<(bool, Option<String>)>;
+
+    fn to_json(&self) -> String {
+        let mut line = String::from("{");
+        for (i, (name, (is_string, value))) in Self::COLUMNS.iter().zip(self.values()).enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            let _ = write!(line, "\"{name}\":");
+            match value {
+                None => line.push_str("null"),
+                Some(v) if is_string => push_json_string(&mut line, &v),
+                Some(v) => line.push_str(&v),
+            }
+        }
+        line.push('}');
+        line
+    }
+
+    fn to_csv(&self) -> String {
+        let fields: Vec<String> = self
+            .values()
+            .into_iter()
+            .map(|(_, value)| csv_field(value.as_deref().unwrap_or("")))
+            .collect();
+        fields.join(",")
+    }
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Writes report rows to a file as they arrive.
+pub struct ReportWriter<T = Record> {
+    out: BufWriter<File>,
+    format: ReportFormat,
+    row: PhantomData<fn(&T)>,
+}
+
+impl<T: Row> ReportWriter<T> {
+    /// Creates the report file, writing the CSV header if needed.
+    pub fn create(path: &Path, format: ReportFormat) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create report file {}", path.display()))?;
+        let mut writer = Self {
+            out: BufWriter::new(file),
+            format,
+            row: PhantomData,
+        };
+        if format == ReportFormat::Csv {
+            writeln!(writer.out, "{}", T::COLUMNS.join(",")).context("Failed to write report")?;
+        }
+        Ok(writer)
+    }
+
+    /// Appends one row.
+    pub fn write(&mut self, row: &T) -> Result<()> {
+        let line = match self.format {
+            ReportFormat::Jsonl => row.to_json(),
+            ReportFormat::Csv => row.to_csv(),
+        };
+        writeln!(self.out, "{line}").context("Failed to write report")
+    }
+
+    /// Flushes buffered records to disk.
+    pub fn finish(mut self) -> Result<()> {
+        self.out.flush().context("Failed to write report")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis(keep: std::ops::Range<u64>) -> Analysis {
+        Analysis {
+            sample_rate: 16_000,
+            channels: 2,
+            total_frames: 4800,
+            padded: keep.clone(),
+            detected: keep.clone(),
+            coarse: keep.clone(),
+            output: Some(Splice::new(keep.clone())),
+            segments: None,
+            rejection: keep.is_empty().then_some(Rejection::Silent),
+            keep,
+            threshold_db: -50.0,
+            start_threshold_db: Some(-50.0),
+            end_threshold_db: Some(-50.0),
+            release_db: None,
+            noise_floor_db: None,
+            reference_db: None,
+            capped_start: false,
+            capped_end: false,
+        }
+    }
+
+    #[test]
+    fn test_status_and_trimmed_ms() {
+        let path = Path::new("a/b.wav");
+        let trimmed = Record::from_analysis(path, &analysis(1600..3200));
+        assert_eq!(trimmed.status, Status::Trimmed);
+        assert_eq!(trimmed.output_frames, Some(1600));
+        assert_eq!(trimmed.leading_ms, Some(100.0));
+        assert_eq!(trimmed.trailing_ms, Some(100.0));
+
+        let unchanged = Record::from_analysis(path, &analysis(0..4800));
+        assert_eq!(unchanged.status, Status::Unchanged);
+        let silent = Record::from_analysis(path, &analysis(0..0));
+        assert_eq!(silent.status, Status::AllSilent);
+        assert_eq!(silent.output_frames, Some(0));
+        assert_eq!(silent.leading_ms, Some(300.0));
+    }
+
+    #[test]
+    fn test_errors_are_categorized() {
+        let path = Path::new("x.wav");
+        let unsupported = anyhow::Error::new(Unsupported("WAV format tag 0x0002".into()));
+        let record = Record::from_error(path, &unsupported);
+        assert_eq!(record.status, Status::Skipped);
+        assert_eq!(record.error_category, Some("unsupported"));
+
+        let io = anyhow::Error::new(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context("Failed to open input WAV file");
+        assert_eq!(Record::from_error(path, &io).error_category, Some("io"));
+        let truncated = anyhow::Error::new(io::Error::from(io::ErrorKind::UnexpectedEof))
+            .context(Malformed)
+            .context("x.wav");
+        assert_eq!(
+            Record::from_error(path, &truncated).error_category,
+            Some("invalid")
+        );
+        let rejected = anyhow::Error::new(Rejection::TooShort).context("in.wav");
+        let record = Record::from_error(path, &rejected);
+        assert_eq!(record.status, Status::TooShort);
+        assert_eq!(record.error_category, Some("rejected"));
+
+        let invalid = anyhow::anyhow!("Not a RIFF/WAVE file");
+        let record = Record::from_error(path, &invalid);
+        assert_eq!(record.status, Status::Error);
+        assert_eq!(record.error_category, Some("invalid"));
+    }
+
+    #[test]
+    fn test_json_and_csv_encoding() {
+        let mut record = Record::from_error(
+            Path::new("dir/odd \"name\", 1.wav"),
+            &anyhow::anyhow!("bad\nthing"),
+        );
+        record.sample_rate = Some(48_000);
+        assert_eq!(
+            record.to_json(),
+            "{\"path\":\"dir/odd \\\"name\\\", 1.wav\",\"status\":\"error\",\
+             \"error_category\":\"invalid\",\"error_message\":\"bad\\nthing\",\
+             \"sample_rate\":48000,\"channels\":null,\"input_frames\":null,\
+             \"output_frames\":null,\"leading_ms\":null,\"trailing_ms\":null,\
+             \"pad_start_ms\":null,\"pad_end_ms\":null,\"pauses\":null,\"pause_removed_ms\":null,\
+             \"segments\":null,\"threshold_db\":null,\"release_db\":null,\
+             \"noise_floor_db\":null,\"reference_db\":null,\"snap_start_frames\":null,\
+             \"snap_end_frames\":null,\"capped_start\":null,\"capped_end\":null,\
+             \"start_frame\":null,\"end_frame\":null,\"detected_start_frame\":null,\
+             \"detected_end_frame\":null,\"coarse_start_frame\":null,\"coarse_end_frame\":null}"
+        );
+        assert_eq!(
+            record.to_csv(),
+            "\"dir/odd \"\"name\"\", 1.wav\",error,invalid,\"bad\nthing\",48000,,,,,,,,,,,,,,,,,,,,,,,,"
+        );
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ReportFormat::from_path(Path::new("r.CSV")),
+            ReportFormat::Csv
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("r.jsonl")),
+            ReportFormat::Jsonl
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("report")),
+            ReportFormat::Jsonl
+        );
+    }
+}
@@ -0,0 +1,627 @@
+//! Minimal RIFF/WAVE container handling, used to carry non-audio chunks through trimming.
+//!
+//! Reads plain RIFF, RF64/BW64 and Sony Wave64 files. Output is always written as RIFF, or as
+//! RF64 when it would not fit in RIFF's 32-bit sizes.
+
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavSpec};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Wave64 GUID of the outer `riff` chunk, as laid out on disk.
+const W64_RIFF: [u8; 16] = [
+    b'r', b'i', b'f', b'f', 0x2E, 0x91, 0xCF, 0x11, 0xA5, 0xD6, 0x28, 0xDB, 0x04, 0xC1, 0x00, 0x00,
+];
+/// Wave64 GUID of the `list` chunk, which maps to RIFF `LIST`.
+const W64_LIST: [u8; 16] = [
+    b'l', b'i', b's', b't', 0x2F, 0x91, 0xCF, 0x11, 0xA5, 0xD6, 0x28, 0xDB, 0x04, 0xC1, 0x00, 0x00,
+];
+/// Wave64 GUIDs for `wave`, `fmt `, `data`, `fact`, ... are the RIFF fourcc followed by this.
+const W64_FOURCC_SUFFIX: [u8; 12] = [
+    0xF3, 0xAC, 0xD3, 0x11, 0x8C, 0xD1, 0x00, 0xC0, 0x4F, 0x8E, 0xDB, 0x8A,
+];
+/// Size field value meaning "look up the real size in `ds64`".
+const RF64_PLACEHOLDER: u32 = u32::MAX;
+
+/// Error for well-formed files whose audio layout this tool does not handle.
+#[derive(Debug)]
+pub struct Unsupported(pub String);
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unsupported {}", self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Context for a failure to read a file's chunk layout, which means the file is not a valid
+/// WAV file whatever the underlying cause (often just an early end of file).
+#[derive(Debug)]
+pub struct Malformed;
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Failed to parse WAV chunks")
+    }
+}
+
+/// A single top-level RIFF chunk held in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    /// Four-character chunk identifier, e.g. `*b"bext"`.
+    pub id: [u8; 4],
+    /// Chunk body without the header or pad byte.
+    pub data: Vec<u8>,
+}
+
+/// Top-level layout of a WAVE file: every chunk except `data`, in file order, plus the location
+/// of the audio data so it can be streamed rather than loaded.
+#[derive(Clone, Debug)]
+pub struct WavLayout {
+    /// All non-`data` chunks (including `fmt `) in their original order. RF64 `ds64` chunks are
+    /// not included; they are regenerated on write when needed.
+    pub chunks: Vec<Chunk>,
+    /// Number of entries in `chunks` that precede the `data` chunk.
+    pub data_index: usize,
+    /// Absolute offset of the first byte of audio data in the source file.
+    pub data_offset: u64,
+    /// Length of the audio data in bytes.
+    pub data_len: u64,
+}
+
+/// Incrementally collects chunks while walking a container.
+#[derive(Default)]
+struct LayoutBuilder {
+    chunks: Vec<Chunk>,
+    data: Option<(usize, u64, u64)>,
+}
+
+impl LayoutBuilder {
+    /// Records the chunk `id` whose body of `size` bytes starts at the reader's position.
+    fn push<R: Read>(&mut self, reader: &mut R, id: [u8; 4], start: u64, size: u64) -> Result<()> {
+        if &id == b"data" {
+            if self.data.is_none() {
+                self.data = Some((self.chunks.len(), start, size));
+            }
+        } else {
+            let mut body = vec![0u8; size as usize];
+            reader
+                .read_exact(&mut body)
+                .with_context(|| format!("Failed to read '{}' chunk", fourcc(&id)))?;
+            self.chunks.push(Chunk { id, data: body });
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<WavLayout> {
+        let (data_index, data_offset, data_len) =
+            self.data.context("WAV file has no data chunk")?;
+        let layout = WavLayout {
+            chunks: self.chunks,
+            data_index,
+            data_offset,
+            data_len,
+        };
+        if layout.chunk(b"fmt ").is_none() {
+            anyhow::bail!("WAV file has no fmt chunk");
+        }
+        Ok(layout)
+    }
+}
+
+impl WavLayout {
+    /// Parses the chunk list of a RIFF, RF64/BW64 or Wave64 stream, reading every chunk except
+    /// `data`.
+    ///
+    /// Chunk sizes that run past the end of the stream (as left by interrupted recorders) are
+    /// clamped to the available bytes.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        reader
+            .read_exact(&mut header)
+            .context("Failed to read RIFF header")?;
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        match &header[0..4] {
+            b"RIFF" | b"RF64" | b"BW64" if &header[8..12] == b"WAVE" => {
+                Self::read_riff(reader, file_len)
+            }
+            _ if header[..] == W64_RIFF[..12] => Self::read_wave64(reader, file_len),
+            _ => anyhow::bail!("Not a RIFF/WAVE, RF64 or Wave64 file"),
+        }
+    }
+
+    /// Walks the chunks of a RIFF or RF64/BW64 file, resolving 64-bit sizes through `ds64`.
+    fn read_riff<R: Read + Seek>(reader: &mut R, file_len: u64) -> Result<Self> {
+        let mut builder = LayoutBuilder::default();
+        let mut ds64: Option<Ds64> = None;
+        let mut pos = 12u64;
+        while pos + 8 <= file_len {
+            reader.seek(SeekFrom::Start(pos))?;
+            let mut head = [0u8; 8];
+            reader
+                .read_exact(&mut head)
+                .context("Failed to read chunk header")?;
+            let id = [head[0], head[1], head[2], head[3]];
+            let body_start = pos + 8;
+            let raw_size = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+            let size = match &ds64 {
+                Some(ds64) if raw_size == RF64_PLACEHOLDER => ds64.size_of(&id),
+                _ => u64::from(raw_size),
+            }
+            .min(file_len - body_start);
+
+            if &id == b"ds64" && ds64.is_none() {
+                let mut body = vec![0u8; size as usize];
+                reader
+                    .read_exact(&mut body)
+                    .context("Failed to read 'ds64' chunk")?;
+                ds64 = Some(Ds64::parse(&body)?);
+            } else {
+                builder.push(reader, id, body_start, size)?;
+            }
+            pos = body_start + size + (size & 1);
+        }
+        builder.finish()
+    }
+
+    /// Walks the GUID-tagged, 8-byte aligned chunks of a Sony Wave64 file.
+    ///
+    /// Chunks whose GUID has no RIFF fourcc equivalent cannot be written to RIFF and are skipped.
+    fn read_wave64<R: Read + Seek>(reader: &mut R, file_len: u64) -> Result<Self> {
+        let mut header = [0u8; 40];
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .read_exact(&mut header)
+            .context("Failed to read Wave64 header")?;
+        if header[..16] != W64_RIFF || wave64_fourcc(header[24..40].try_into()?) != Some(*b"wave") {
+            anyhow::bail!("Not a Wave64 WAVE file");
+        }
+
+        let mut builder = LayoutBuilder::default();
+        let mut pos = 40u64;
+        while pos + 24 <= file_len {
+            reader.seek(SeekFrom::Start(pos))?;
+            let mut head = [0u8; 24];
+            reader
+                .read_exact(&mut head)
+                .context("Failed to read Wave64 chunk header")?;
+            let body_start = pos + 24;
+            // Wave64 sizes include the 24-byte header.
+            let size = u64::from_le_bytes(head[16..24].try_into()?)
+                .saturating_sub(24)
+                .min(file_len - body_start);
+
+            let guid: [u8; 16] = head[..16].try_into()?;
+            if let Some(id) = wave64_fourcc(&guid) {
+                builder.push(reader, id, body_start, size)?;
+            }
+            pos = body_start + size.next_multiple_of(8);
+        }
+        builder.finish()
+    }
+
+    /// Returns the first chunk with the given identifier.
+    pub fn chunk(&self, id: &[u8; 4]) -> Option<&Chunk> {
+        self.chunks.iter().find(|c| &c.id == id)
+    }
+
+    /// Returns the first chunk with the given identifier for modification.
+    pub fn chunk_mut(&mut self, id: &[u8; 4]) -> Option<&mut Chunk> {
+        self.chunks.iter_mut().find(|c| &c.id == id)
+    }
+
+    /// Returns the size in bytes of one interleaved frame, as declared by the `fmt ` chunk.
+    pub fn block_align(&self) -> Result<u64> {
+        let fmt = self.chunk(b"fmt ").context("WAV file has no fmt chunk")?;
+        if fmt.data.len() < 16 {
+            anyhow::bail!("fmt chunk is too short");
+        }
+        let block_align = u16::from_le_bytes([fmt.data[12], fmt.data[13]]);
+        if block_align == 0 {
+            anyhow::bail!("fmt chunk declares a block alignment of zero");
+        }
+        Ok(u64::from(block_align))
+    }
+
+    /// Drops every chunk except `fmt ` and `fact`, which describe the audio itself.
+    pub fn strip_metadata(&mut self) {
+        let mut index = 0;
+        let mut data_index = self.data_index;
+        self.chunks.retain(|c| {
+            let keep = &c.id == b"fmt " || &c.id == b"fact";
+            if !keep && index < self.data_index {
+                data_index -= 1;
+            }
+            index += 1;
+            keep
+        });
+        self.data_index = data_index;
+    }
+
+    /// Updates the per-channel sample count in the `fact` chunk, if present.
+    pub fn set_fact_sample_length(&mut self, frames: u64) {
+        if let Some(fact) = self.chunk_mut(b"fact")
+            && fact.data.len() >= 4
+        {
+            let frames = u32::try_from(frames).unwrap_or(u32::MAX);
+            fact.data[0..4].copy_from_slice(&frames.to_le_bytes());
+        }
+    }
+
+    /// Returns the stream format declared by the `fmt ` chunk, resolving
+    /// `WAVE_FORMAT_EXTENSIBLE` to its PCM or IEEE float subformat.
+    pub fn spec(&self) -> Result<WavSpec> {
+        let fmt = &self
+            .chunk(b"fmt ")
+            .context("WAV file has no fmt chunk")?
+            .data;
+        if fmt.len() < 16 {
+            anyhow::bail!("fmt chunk is too short");
+        }
+        let field = |offset: usize| u16::from_le_bytes([fmt[offset], fmt[offset + 1]]);
+        let format_tag = match field(0) {
+            0xFFFE if fmt.len() >= 40 => field(24),
+            tag => tag,
+        };
+        let sample_format = match format_tag {
+            1 => SampleFormat::Int,
+            3 => SampleFormat::Float,
+            tag => anyhow::bail!(Unsupported(format!("WAV format tag {tag:#06x}"))),
+        };
+        let spec = WavSpec {
+            channels: field(2),
+            sample_rate: u32::from_le_bytes(fmt[4..8].try_into()?),
+            bits_per_sample: field(14),
+            sample_format,
+        };
+        if spec.channels == 0 || spec.sample_rate == 0 {
+            anyhow::bail!("fmt chunk declares no channels or a zero sample rate");
+        }
+        if !spec.bits_per_sample.is_multiple_of(8)
+            || self.block_align()? != u64::from(spec.channels) * u64::from(spec.bits_per_sample / 8)
+        {
+            anyhow::bail!(Unsupported(format!(
+                "sample layout with {} bits per sample and block align {}",
+                spec.bits_per_sample,
+                self.block_align()?
+            )));
+        }
+        Ok(spec)
+    }
+
+    /// Writes a complete WAVE file with this layout's chunks and `data_len` bytes of audio taken
+    /// from `data`.
+    ///
+    /// The file is plain RIFF when every size fits in 32 bits, and RF64 (with a `ds64` chunk
+    /// carrying the 64-bit sizes) otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `data` ends early or I/O fails.
+    pub fn write<W: Write, D: Read>(
+        &self,
+        writer: &mut W,
+        data: &mut D,
+        data_len: u64,
+    ) -> Result<()> {
+        let chunks_len: u64 = self
+            .chunks
+            .iter()
+            .map(|c| padded_len(c.data.len() as u64) + 8)
+            .sum();
+        let riff_len = 4 + chunks_len + 8 + padded_len(data_len);
+        match u32::try_from(riff_len) {
+            Ok(riff_len) if riff_len != RF64_PLACEHOLDER => {
+                self.write_as(writer, data, data_len, riff_len, None)
+            }
+            _ => {
+                let ds64 = Ds64 {
+                    riff_len: riff_len + 8 + DS64_LEN as u64,
+                    data_len,
+                    sample_count: data_len / self.block_align()?,
+                    table: HashMap::new(),
+                };
+                self.write_as(writer, data, data_len, RF64_PLACEHOLDER, Some(ds64))
+            }
+        }
+    }
+
+    fn write_as<W: Write, D: Read>(
+        &self,
+        writer: &mut W,
+        data: &mut D,
+        data_len: u64,
+        riff_len: u32,
+        ds64: Option<Ds64>,
+    ) -> Result<()> {
+        let data_size = match &ds64 {
+            Some(_) => RF64_PLACEHOLDER,
+            None => data_len as u32,
+        };
+        writer.write_all(if ds64.is_some() { b"RF64" } else { b"RIFF" })?;
+        writer.write_all(&riff_len.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        if let Some(ds64) = &ds64 {
+            writer.write_all(b"ds64")?;
+            writer.write_all(&(DS64_LEN as u32).to_le_bytes())?;
+            writer.write_all(&ds64.riff_len.to_le_bytes())?;
+            writer.write_all(&ds64.data_len.to_le_bytes())?;
+            writer.write_all(&ds64.sample_count.to_le_bytes())?;
+            writer.write_all(&0u32.to_le_bytes())?;
+        }
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if index == self.data_index {
+                write_data_chunk(writer, data, data_len, data_size)?;
+            }
+            write_chunk(writer, chunk)?;
+        }
+        if self.data_index >= self.chunks.len() {
+            write_data_chunk(writer, data, data_len, data_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Size of a `ds64` chunk body without a chunk-size table.
+const DS64_LEN: usize = 28;
+
+/// Contents of an RF64 `ds64` chunk: the 64-bit sizes that do not fit in the RIFF headers.
+#[derive(Debug)]
+struct Ds64 {
+    riff_len: u64,
+    data_len: u64,
+    sample_count: u64,
+    /// 64-bit sizes of chunks other than `data`, by identifier.
+    table: HashMap<[u8; 4], u64>,
+}
+
+impl Ds64 {
+    fn parse(body: &[u8]) -> Result<Self> {
+        if body.len() < DS64_LEN {
+            anyhow::bail!("ds64 chunk is too short");
+        }
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(body[offset..offset + 8].try_into().unwrap());
+        let entries = u32::from_le_bytes(body[24..28].try_into()?) as usize;
+        let table = body[DS64_LEN..]
+            .chunks_exact(12)
+            .take(entries)
+            .map(|entry| {
+                let id = [entry[0], entry[1], entry[2], entry[3]];
+                (id, u64::from_le_bytes(entry[4..12].try_into().unwrap()))
+            })
+            .collect();
+        Ok(Self {
+            riff_len: u64_at(0),
+            data_len: u64_at(8),
+            sample_count: u64_at(16),
+            table,
+        })
+    }
+
+    /// Returns the real size of a chunk whose 32-bit size field is the RF64 placeholder.
+    fn size_of(&self, id: &[u8; 4]) -> u64 {
+        if id == b"data" {
+            self.data_len
+        } else {
+            self.table
+                .get(id)
+                .copied()
+                .unwrap_or(u64::from(RF64_PLACEHOLDER))
+        }
+    }
+}
+
+/// Maps a Wave64 chunk GUID to the equivalent RIFF fourcc, if there is one.
+fn wave64_fourcc(guid: &[u8; 16]) -> Option<[u8; 4]> {
+    let id = [guid[0], guid[1], guid[2], guid[3]];
+    if *guid == W64_LIST {
+        Some(*b"LIST")
+    } else if guid[4..] == W64_FOURCC_SUFFIX {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Returns a printable form of a chunk identifier.
+pub fn fourcc(id: &[u8; 4]) -> String {
+    String::from_utf8_lossy(id).into_owned()
+}
+
+/// Returns `len` rounded up to the even size RIFF chunks occupy on disk.
+fn padded_len(len: u64) -> u64 {
+    len + (len & 1)
+}
+
+fn write_chunk<W: Write>(writer: &mut W, chunk: &Chunk) -> Result<()> {
+    writer.write_all(&chunk.id)?;
+    writer.write_all(&(chunk.data.len() as u32).to_le_bytes())?;
+    writer.write_all(&chunk.data)?;
+    if chunk.data.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+fn write_data_chunk<W: Write, D: Read>(
+    writer: &mut W,
+    data: &mut D,
+    data_len: u64,
+    size_field: u32,
+) -> Result<()> {
+    writer.write_all(b"data")?;
+    writer.write_all(&size_field.to_le_bytes())?;
+    let copied = io::copy(&mut data.take(data_len), writer).context("Failed to copy audio data")?;
+    if copied != data_len {
+        anyhow::bail!("Audio data ended early ({copied} of {data_len} bytes)");
+    }
+    if data_len % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], data: Vec<u8>) -> Chunk {
+        Chunk { id: *id, data }
+    }
+
+    fn fmt_chunk() -> Chunk {
+        // Mono 8-bit PCM at 8 kHz: block align 1, so odd data lengths need a pad byte.
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&8u16.to_le_bytes());
+        chunk(b"fmt ", fmt)
+    }
+
+    #[test]
+    fn test_round_trip_preserves_chunk_order_and_padding() {
+        let layout = WavLayout {
+            chunks: vec![
+                fmt_chunk(),
+                chunk(b"bext", vec![1, 2, 3]),
+                chunk(b"LIST", b"INFOINAM\x02\x00\x00\x00A\x00".to_vec()),
+            ],
+            data_index: 2,
+            data_offset: 0,
+            data_len: 0,
+        };
+        let audio = [10u8, 20, 30, 40, 50];
+        let mut bytes = Vec::new();
+        layout
+            .write(&mut bytes, &mut &audio[..], audio.len() as u64)
+            .unwrap();
+        assert_eq!(bytes.len() % 2, 0);
+
+        let mut cursor = Cursor::new(&bytes);
+        let parsed = WavLayout::read(&mut cursor).unwrap();
+        assert_eq!(parsed.chunks, layout.chunks);
+        assert_eq!(parsed.data_index, 2);
+        assert_eq!(parsed.data_len, 5);
+        assert_eq!(parsed.block_align().unwrap(), 1);
+        let start = parsed.data_offset as usize;
+        assert_eq!(&bytes[start..start + 5], &audio);
+        let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        assert_eq!(riff_len as usize, bytes.len() - 8);
+    }
+
+    #[test]
+    fn test_strip_metadata_keeps_format_chunks() {
+        let mut layout = WavLayout {
+            chunks: vec![
+                chunk(b"JUNK", vec![0; 4]),
+                fmt_chunk(),
+                chunk(b"fact", vec![0; 4]),
+                chunk(b"iXML", vec![b'x']),
+            ],
+            data_index: 3,
+            data_offset: 0,
+            data_len: 0,
+        };
+        layout.strip_metadata();
+        layout.set_fact_sample_length(1234);
+        let ids: Vec<_> = layout.chunks.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![*b"fmt ", *b"fact"]);
+        assert_eq!(layout.data_index, 2);
+        assert_eq!(layout.chunk(b"fact").unwrap().data, 1234u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_rf64_round_trip_uses_ds64_sizes() {
+        let layout = WavLayout {
+            chunks: vec![fmt_chunk(), chunk(b"iXML", vec![b'x'; 3])],
+            data_index: 1,
+            data_offset: 0,
+            data_len: 0,
+        };
+        let audio = [1u8, 2, 3, 4];
+        let ds64 = Ds64 {
+            riff_len: 1234,
+            data_len: audio.len() as u64,
+            sample_count: audio.len() as u64,
+            table: HashMap::new(),
+        };
+        let mut bytes = Vec::new();
+        layout
+            .write_as(&mut bytes, &mut &audio[..], 4, RF64_PLACEHOLDER, Some(ds64))
+            .unwrap();
+        assert_eq!(&bytes[0..4], b"RF64");
+        assert_eq!(&bytes[12..16], b"ds64");
+
+        let parsed = WavLayout::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(parsed.chunks, layout.chunks);
+        assert_eq!(parsed.data_len, 4);
+        let start = parsed.data_offset as usize;
+        assert_eq!(&bytes[start..start + 4], &audio);
+    }
+
+    #[test]
+    fn test_reads_wave64() {
+        fn w64_chunk(bytes: &mut Vec<u8>, guid: &[u8], body: &[u8]) {
+            bytes.extend_from_slice(guid);
+            bytes.extend_from_slice(&(24 + body.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(body);
+            bytes.resize(bytes.len().next_multiple_of(8), 0);
+        }
+        let fourcc_guid = |id: &[u8; 4]| [&id[..], &W64_FOURCC_SUFFIX[..]].concat();
+
+        let mut bytes = W64_RIFF.to_vec();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&fourcc_guid(b"wave"));
+        w64_chunk(&mut bytes, &fourcc_guid(b"fmt "), &fmt_chunk().data);
+        w64_chunk(&mut bytes, &[0xAB; 16], b"vendor");
+        w64_chunk(&mut bytes, &fourcc_guid(b"data"), &[9, 8, 7, 6, 5]);
+        w64_chunk(&mut bytes, &W64_LIST, b"INFO");
+
+        let parsed = WavLayout::read(&mut Cursor::new(&bytes)).unwrap();
+        let ids: Vec<_> = parsed.chunks.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![*b"fmt ", *b"LIST"]);
+        assert_eq!(parsed.data_index, 1);
+        assert_eq!(parsed.data_len, 5);
+        let start = parsed.data_offset as usize;
+        assert_eq!(&bytes[start..start + 5], &[9, 8, 7, 6, 5]);
+        assert_eq!(parsed.spec().unwrap().sample_rate, 8000);
+    }
+
+    #[test]
+    fn test_spec_resolves_extensible_float() {
+        let mut fmt = Vec::new();
+        for field in [0xFFFEu16, 2] {
+            fmt.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt.extend_from_slice(&48_000u32.to_le_bytes());
+        fmt.extend_from_slice(&(48_000u32 * 8).to_le_bytes());
+        for field in [8u16, 32, 22, 32] {
+            fmt.extend_from_slice(&field.to_le_bytes());
+        }
+        fmt.extend_from_slice(&3u32.to_le_bytes());
+        fmt.extend_from_slice(&3u16.to_le_bytes());
+        fmt.extend_from_slice(&[0; 14]);
+        let layout = WavLayout {
+            chunks: vec![chunk(b"fmt ", fmt)],
+            data_index: 1,
+            data_offset: 0,
+            data_len: 0,
+        };
+        let spec = layout.spec().unwrap();
+        assert_eq!(spec.sample_format, SampleFormat::Float);
+        assert_eq!((spec.channels, spec.bits_per_sample), (2, 32));
+    }
+
+    #[test]
+    fn test_rejects_non_wave() {
+        let mut cursor = Cursor::new(b"RIFF\x04\x00\x00\x00AVI ".to_vec());
+        assert!(WavLayout::read(&mut cursor).is_err());
+    }
+}
@@ -0,0 +1,444 @@
+//! Sample decoding and frame sources that feed silence detection.
+
+use crate::detect::{ChannelPolicy, FrameSource};
+use crate::riff::{Unsupported, WavLayout};
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavSpec};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// A PCM sample type that can be decoded from WAV data and mapped into the normalized
+/// `[-1.0, 1.0]` float domain.
+pub trait PcmSample: Copy {
+    /// Decodes one little-endian sample occupying all of `bytes`.
+    fn decode(bytes: &[u8]) -> Self;
+
+    /// Returns the sample as a fraction of full scale for a file with the given bit depth.
+    fn to_normalized(self, bits_per_sample: u16) -> f64;
+
+    /// Encodes the sample little-endian into all of `bytes`, the inverse of [`decode`].
+    ///
+    /// [`decode`]: PcmSample::decode
+    fn encode(self, bytes: &mut [u8]);
+
+    /// Returns the sample multiplied by `gain`, rounded to the nearest representable value.
+    fn scaled(self, gain: f64) -> Self;
+
+    /// Returns the linear blend from this sample (`t` = 0.0) to `other` (`t` = 1.0), rounded
+    /// to the nearest representable value. The result never exceeds the larger input.
+    fn mixed(self, other: Self, t: f64) -> Self;
+}
+
+impl PcmSample for i8 {
+    /// 8-bit WAV data is unsigned with a midpoint of 128.
+    fn decode(bytes: &[u8]) -> Self {
+        (bytes[0] ^ 0x80) as i8
+    }
+
+    fn to_normalized(self, _bits_per_sample: u16) -> f64 {
+        self as f64 / 128.0
+    }
+
+    fn encode(self, bytes: &mut [u8]) {
+        bytes[0] = self as u8 ^ 0x80;
+    }
+
+    fn scaled(self, gain: f64) -> Self {
+        (self as f64 * gain).round() as i8
+    }
+
+    fn mixed(self, other: Self, t: f64) -> Self {
+        (self as f64 + (other as f64 - self as f64) * t).round() as i8
+    }
+}
+
+impl PcmSample for i16 {
+    fn decode(bytes: &[u8]) -> Self {
+        i16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    fn to_normalized(self, _bits_per_sample: u16) -> f64 {
+        self as f64 / 32768.0
+    }
+
+    fn encode(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn scaled(self, gain: f64) -> Self {
+        (self as f64 * gain).round() as i16
+    }
+
+    fn mixed(self, other: Self, t: f64) -> Self {
+        (self as f64 + (other as f64 - self as f64) * t).round() as i16
+    }
+}
+
+impl PcmSample for i32 {
+    /// Accepts 24-bit (sign-extended) or 32-bit samples.
+    fn decode(bytes: &[u8]) -> Self {
+        match *bytes {
+            [b0, b1, b2] => i32::from_le_bytes([0, b0, b1, b2]) >> 8,
+            _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+
+    fn to_normalized(self, bits_per_sample: u16) -> f64 {
+        self as f64 / (1u64 << (bits_per_sample - 1)) as f64
+    }
+
+    fn encode(self, bytes: &mut [u8]) {
+        let le = self.to_le_bytes();
+        let len = bytes.len();
+        bytes.copy_from_slice(&le[..len]);
+    }
+
+    fn scaled(self, gain: f64) -> Self {
+        (self as f64 * gain).round() as i32
+    }
+
+    fn mixed(self, other: Self, t: f64) -> Self {
+        (self as f64 + (other as f64 - self as f64) * t).round() as i32
+    }
+}
+
+impl PcmSample for f32 {
+    fn decode(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn to_normalized(self, _bits_per_sample: u16) -> f64 {
+        self as f64
+    }
+
+    fn encode(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn scaled(self, gain: f64) -> Self {
+        (self as f64 * gain) as f32
+    }
+
+    fn mixed(self, other: Self, t: f64) -> Self {
+        (self as f64 + (other as f64 - self as f64) * t) as f32
+    }
+}
+
+/// Frames read and processed per refill by the readers that rewrite audio on its way to the
+/// output.
+pub const CHUNK_FRAMES: u64 = 4096;
+
+/// Code generic over the sample type, run by [`with_sample_type`] with the type that decodes a
+/// particular WAV format.
+pub trait SampleTypeVisitor {
+    /// What the code produces.
+    type Output;
+
+    /// Runs the code with samples decoded as `T`.
+    fn visit<T: PcmSample + 'static>(self) -> Self::Output;
+}
+
+/// Runs `visitor` with the [`PcmSample`] type for the samples of `spec`: `i8` or `i16` for 8- or
+/// 16-bit PCM, `i32` for 24- or 32-bit PCM, and `f32` for 32-bit float.
+///
+/// # Errors
+///
+/// Returns an [`Unsupported`] error for any other sample format.
+pub fn with_sample_type<V: SampleTypeVisitor>(spec: WavSpec, visitor: V) -> Result<V::Output> {
+    Ok(match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, 8) => visitor.visit::<i8>(),
+        (SampleFormat::Int, 16) => visitor.visit::<i16>(),
+        (SampleFormat::Int, 24 | 32) => visitor.visit::<i32>(),
+        (SampleFormat::Float, 32) => visitor.visit::<f32>(),
+        (format, bits) => anyhow::bail!(Unsupported(format!(
+            "WAV format: {}-bit {:?} (expected 8/16/24/32-bit PCM or 32-bit float)",
+            bits, format
+        ))),
+    })
+}
+
+/// Appends the detection power of each interleaved frame in `samples` to `out`.
+fn push_powers<T: PcmSample>(
+    samples: &[T],
+    spec: WavSpec,
+    policy: ChannelPolicy,
+    out: &mut Vec<f64>,
+) {
+    out.extend(
+        samples
+            .chunks_exact(spec.channels as usize)
+            .map(|frame| policy.frame_power(frame, spec.bits_per_sample)),
+    );
+}
+
+/// Frame source over interleaved samples already in memory, used to exercise detection in tests.
+#[cfg(test)]
+pub struct SliceSource<'a, T> {
+    samples: &'a [T],
+    spec: WavSpec,
+    policy: ChannelPolicy,
+}
+
+#[cfg(test)]
+impl<'a, T: PcmSample> SliceSource<'a, T> {
+    /// Wraps interleaved `samples` laid out as described by `spec`.
+    pub fn new(samples: &'a [T], spec: WavSpec, policy: ChannelPolicy) -> Self {
+        Self {
+            samples,
+            spec,
+            policy,
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T: PcmSample> FrameSource for SliceSource<'_, T> {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.spec.channels as usize
+    }
+
+    fn channels(&self) -> usize {
+        self.spec.channels as usize
+    }
+
+    fn read_powers(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()> {
+        let channels = self.spec.channels as usize;
+        out.clear();
+        push_powers(
+            &self.samples[range.start * channels..range.end * channels],
+            self.spec,
+            self.policy,
+            out,
+        );
+        Ok(())
+    }
+
+    fn read_samples(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()> {
+        let channels = self.spec.channels as usize;
+        out.clear();
+        out.extend(
+            self.samples[range.start * channels..range.end * channels]
+                .iter()
+                .map(|s| s.to_normalized(self.spec.bits_per_sample)),
+        );
+        Ok(())
+    }
+}
+
+/// Formats and signals shared by the tests of every module.
+#[cfg(test)]
+pub mod fixtures {
+    use hound::{SampleFormat, WavSpec};
+    use std::f64::consts::PI;
+
+    /// 16-bit mono at 16 kHz, the format most tests use.
+    pub const MONO: WavSpec = WavSpec {
+        channels: 1,
+        sample_rate: 16_000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    /// 16-bit stereo at 16 kHz.
+    pub const STEREO: WavSpec = WavSpec {
+        channels: 2,
+        ..MONO
+    };
+
+    /// Returns a 16 kHz spec with the given layout.
+    pub fn spec(channels: u16, bits_per_sample: u16, sample_format: SampleFormat) -> WavSpec {
+        WavSpec {
+            channels,
+            sample_rate: 16_000,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+
+    /// A tone of `hz` at 16 kHz and about a tenth of full scale.
+    pub fn tone(hz: f64, frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|i| (3277.0 * (2.0 * PI * hz * i as f64 / 16_000.0).sin()).round() as i16)
+            .collect()
+    }
+
+    /// Bursts of a tone at about a tenth of full scale, of the given lengths in frames, each
+    /// followed by a silence of the given length.
+    pub fn bursts(parts: &[(usize, usize)]) -> Vec<i16> {
+        let mut samples = Vec::new();
+        for &(tone, gap) in parts {
+            samples.extend((0..tone).map(|i| if i % 2 == 0 { 3277i16 } else { -3277 }));
+            samples.extend(vec![0i16; gap]);
+        }
+        samples
+    }
+}
+
+/// Frame source that reads the data chunk of a WAV file on demand, one window at a time, so
+/// memory use does not depend on the file length.
+pub struct FileSource<T> {
+    file: File,
+    data_offset: u64,
+    block_align: usize,
+    frames: usize,
+    spec: WavSpec,
+    policy: ChannelPolicy,
+    bytes: Vec<u8>,
+    samples: Vec<T>,
+}
+
+impl<T: PcmSample> FileSource<T> {
+    /// Reads the whole frames of the data chunk described by `layout` from `file`.
+    pub fn new(file: File, layout: &WavLayout, policy: ChannelPolicy) -> Result<Self> {
+        let block_align = layout.block_align()?;
+        Ok(Self {
+            file,
+            data_offset: layout.data_offset,
+            block_align: block_align as usize,
+            frames: usize::try_from(layout.data_len / block_align)?,
+            spec: layout.spec()?,
+            policy,
+            bytes: Vec::new(),
+            samples: Vec::new(),
+        })
+    }
+
+    /// Reads and decodes the interleaved samples of the frames in `range`.
+    fn load(&mut self, range: Range<usize>) -> Result<()> {
+        let sample_len = self.spec.bits_per_sample as usize / 8;
+        self.file
+            .seek(SeekFrom::Start(
+                self.data_offset + (range.start * self.block_align) as u64,
+            ))
+            .context("Failed to seek in audio data")?;
+        self.bytes.resize(range.len() * self.block_align, 0);
+        self.file
+            .read_exact(&mut self.bytes)
+            .context("Failed to read samples")?;
+
+        self.samples.clear();
+        self.samples
+            .extend(self.bytes.chunks_exact(sample_len).map(T::decode));
+        Ok(())
+    }
+}
+
+impl<T: PcmSample> FrameSource for FileSource<T> {
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn channels(&self) -> usize {
+        self.spec.channels as usize
+    }
+
+    fn read_powers(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()> {
+        self.load(range)?;
+        out.clear();
+        push_powers(&self.samples, self.spec, self.policy, out);
+        Ok(())
+    }
+
+    fn read_samples(&mut self, range: Range<usize>, out: &mut Vec<f64>) -> Result<()> {
+        self.load(range)?;
+        out.clear();
+        let bits = self.spec.bits_per_sample;
+        out.extend(self.samples.iter().map(|s| s.to_normalized(bits)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::spec;
+    use super::*;
+    use hound::{SampleFormat, WavWriter};
+
+    fn powers<T: PcmSample>(samples: &[T], spec: WavSpec) -> Vec<f64> {
+        let mut source = SliceSource::new(samples, spec, ChannelPolicy::Max);
+        let mut out = Vec::new();
+        let frames = source.frames();
+        source.read_powers(0..frames, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_decode_samples() {
+        assert_eq!(<i8 as PcmSample>::decode(&[0x80]), 0);
+        assert_eq!(<i8 as PcmSample>::decode(&[0x00]), -128);
+        assert_eq!(<i8 as PcmSample>::decode(&[0xFF]), 127);
+        assert_eq!(<i16 as PcmSample>::decode(&[0x00, 0x80]), i16::MIN);
+        assert_eq!(
+            <i32 as PcmSample>::decode(&[0xFF, 0xFF, 0x7F]),
+            (1 << 23) - 1
+        );
+        assert_eq!(<i32 as PcmSample>::decode(&[0x00, 0x00, 0x80]), -(1 << 23));
+        assert_eq!(<i32 as PcmSample>::decode(&[0, 0, 0, 0x80]), i32::MIN);
+        assert_eq!(<f32 as PcmSample>::decode(&0.25f32.to_le_bytes()), 0.25);
+    }
+
+    #[test]
+    fn test_encode_round_trips() {
+        fn round_trip<T: PcmSample>(bytes: &[u8]) {
+            let mut out = vec![0; bytes.len()];
+            T::decode(bytes).encode(&mut out);
+            assert_eq!(out, bytes);
+        }
+        round_trip::<i8>(&[0x00]);
+        round_trip::<i8>(&[0x80]);
+        round_trip::<i8>(&[0xFF]);
+        round_trip::<i16>(&[0x34, 0x92]);
+        round_trip::<i32>(&[0x01, 0x02, 0x83]);
+        round_trip::<i32>(&[0xFF, 0xFF, 0x7F]);
+        round_trip::<i32>(&[0x01, 0x02, 0x03, 0x84]);
+        round_trip::<f32>(&(-0.75f32).to_le_bytes());
+        assert_eq!(<i32 as PcmSample>::scaled(-(1 << 23), 0.5), -(1 << 22));
+        assert_eq!(<i8 as PcmSample>::scaled(-127, 0.0), 0);
+    }
+
+    #[test]
+    fn test_powers_are_normalized_across_formats() {
+        // Half scale reads as the same level (about -6 dBFS) in every format.
+        let int8 = powers(&[64i8; 4], spec(1, 8, SampleFormat::Int));
+        let int24 = powers(&[1i32 << 22; 4], spec(1, 24, SampleFormat::Int));
+        let int32 = powers(&[1i32 << 30; 4], spec(1, 32, SampleFormat::Int));
+        let float = powers(&[0.5f32; 4], spec(1, 32, SampleFormat::Float));
+        for level in [int8, int24, int32, float] {
+            assert_eq!(level, vec![0.25; 4]);
+        }
+    }
+
+    #[test]
+    fn test_file_source_matches_slice_source() {
+        let path = std::env::temp_dir().join(format!(
+            "wav-files-trim-{}-file-source.wav",
+            std::process::id()
+        ));
+        let wav_spec = spec(2, 24, SampleFormat::Int);
+        let samples: Vec<i32> = (0..200).map(|i| (i - 100) * 40_000).collect();
+        let mut writer = WavWriter::create(&path, wav_spec).unwrap();
+        for &s in &samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let layout = WavLayout::read(&mut file).unwrap();
+        let mut from_file =
+            FileSource::<i32>::new(file, &layout, ChannelPolicy::Channel(1)).unwrap();
+        let mut from_slice = SliceSource::new(&samples, wav_spec, ChannelPolicy::Channel(1));
+        assert_eq!(from_file.frames(), 100);
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        from_file.read_powers(30..70, &mut a).unwrap();
+        from_slice.read_powers(30..70, &mut b).unwrap();
+        assert_eq!(a.len(), 40);
+        assert_eq!(a, b);
+        from_file.read_samples(30..70, &mut a).unwrap();
+        from_slice.read_samples(30..70, &mut b).unwrap();
+        assert_eq!(a.len(), 80);
+        assert_eq!(a, b);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
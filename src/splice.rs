@@ -0,0 +1,310 @@
+//! Joining the kept segments of a file across shortened pauses, crossfading at each join.
+
+use crate::source::{CHUNK_FRAMES, PcmSample, SampleTypeVisitor, with_sample_type};
+use anyhow::Result;
+use hound::WavSpec;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// The input frames that make up the output: one or more segments, in order, each overlapping
+/// the previous one by a crossfade in the output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Splice {
+    /// Kept input frames; disjoint and in order.
+    segments: Vec<Range<u64>>,
+    /// Frames by which each segment overlaps the previous one in the output; zero for the first.
+    overlaps: Vec<u64>,
+}
+
+impl Splice {
+    /// Creates a splice that keeps `keep` as a single segment.
+    pub fn new(keep: Range<u64>) -> Self {
+        Self {
+            segments: vec![keep],
+            overlaps: vec![0],
+        }
+    }
+
+    /// Creates a splice that keeps `keep` but shortens each of `pauses` (disjoint silent
+    /// regions inside it, in order) to `target` frames, crossfading over up to `crossfade`
+    /// frames at each join. The silence left on either side of a join is split evenly, and the
+    /// crossfade never exceeds the silence kept, so joins only ever mix silence. Pauses no
+    /// longer than `target` are left alone.
+    pub fn collapse(keep: Range<u64>, pauses: &[Range<u64>], target: u64, crossfade: u64) -> Self {
+        let mut splice = Self {
+            segments: Vec::with_capacity(pauses.len() + 1),
+            overlaps: Vec::with_capacity(pauses.len() + 1),
+        };
+        let mut start = keep.start;
+        let mut overlap = 0;
+        for pause in pauses.iter().filter(|p| p.end - p.start > target) {
+            let fade = crossfade.min(pause.end - pause.start - target).min(target);
+            let before = (target + fade).div_ceil(2);
+            let after = target + fade - before;
+            splice.segments.push(start..pause.start + before);
+            splice.overlaps.push(overlap);
+            start = pause.end - after;
+            overlap = fade;
+        }
+        splice.segments.push(start..keep.end);
+        splice.overlaps.push(overlap);
+        splice
+    }
+
+    /// Returns the number of frames in the output.
+    pub fn frames(&self) -> u64 {
+        let kept: u64 = self.segments.iter().map(|s| s.end - s.start).sum();
+        kept - self.overlaps.iter().sum::<u64>()
+    }
+
+    /// Returns the first input frame kept.
+    pub fn start(&self) -> u64 {
+        self.segments[0].start
+    }
+
+    /// Returns the number of joins between segments.
+    pub fn joins(&self) -> usize {
+        self.segments.len() - 1
+    }
+
+    /// Returns the number of input frames between the first and last kept frame that are not
+    /// in the output, counting each crossfade once.
+    pub fn removed(&self) -> u64 {
+        let last = self.segments.len() - 1;
+        self.segments[last].end - self.start() - self.frames()
+    }
+
+    /// Returns each segment along with the output frame it begins at.
+    fn placed(&self) -> impl Iterator<Item = (&Range<u64>, u64)> {
+        let mut out = 0;
+        self.segments
+            .iter()
+            .zip(&self.overlaps)
+            .map(move |(segment, overlap)| {
+                out -= overlap;
+                let placed = (segment, out);
+                out += segment.end - segment.start;
+                placed
+            })
+    }
+
+    /// Maps an input frame position onto the output, or `None` if it lies outside every
+    /// segment. Segment ends are inclusive, so a position at a join maps into the crossfade.
+    pub fn map(&self, pos: u64) -> Option<u64> {
+        self.placed()
+            .find(|(segment, _)| (segment.start..=segment.end).contains(&pos))
+            .map(|(segment, out)| out + pos - segment.start)
+    }
+
+    /// Maps a position like [`Splice::map`], moving positions outside every segment to the
+    /// nearest segment edge (the earlier one, at equal distance).
+    pub fn clamp(&self, pos: u64) -> u64 {
+        self.placed()
+            .map(|(segment, out)| {
+                let clamped = pos.clamp(segment.start, segment.end);
+                (clamped.abs_diff(pos), out + clamped - segment.start)
+            })
+            .min_by_key(|&(distance, _)| distance)
+            .map_or(0, |(_, mapped)| mapped)
+    }
+}
+
+/// A stretch of output: input frames copied as they are, or the tail of one segment mixed
+/// into the head of the next.
+enum Piece {
+    Copy(Range<u64>),
+    Crossfade { from: u64, to: u64, frames: u64 },
+}
+
+/// Crossfades one interleaved frame, decoded as `T`, from `from` toward `to` by `t`.
+fn mix_frame<T: PcmSample>(from: &mut [u8], to: &[u8], sample_len: usize, t: f64) {
+    for (a, b) in from
+        .chunks_exact_mut(sample_len)
+        .zip(to.chunks_exact(sample_len))
+    {
+        T::decode(a).mixed(T::decode(b), t).encode(a);
+    }
+}
+
+/// Picks [`mix_frame`] for the sample type of a format.
+struct MixFrame;
+
+impl SampleTypeVisitor for MixFrame {
+    type Output = fn(&mut [u8], &[u8], usize, f64);
+
+    fn visit<T: PcmSample + 'static>(self) -> Self::Output {
+        mix_frame::<T>
+    }
+}
+
+/// Reader over the raw data bytes of a [`Splice`], read from an input positioned anywhere, that
+/// yields exactly [`Splice::frames`] whole frames. Joins are mixed with a linear crossfade, so
+/// the output streams in constant memory and never exceeds the level of its inputs.
+pub struct SpliceReader<R> {
+    inner: R,
+    data_offset: u64,
+    pieces: Vec<Piece>,
+    /// Index of the piece being read and frames already read from it.
+    piece: usize,
+    frame: u64,
+    block_align: usize,
+    sample_len: usize,
+    mix: fn(&mut [u8], &[u8], usize, f64),
+    buf: Vec<u8>,
+    other: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read + Seek> SpliceReader<R> {
+    /// Wraps `inner`, whose data chunk starts at byte `data_offset` and is laid out as
+    /// described by `spec`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `spec` is not a sample format that can be crossfaded.
+    pub fn new(inner: R, data_offset: u64, spec: WavSpec, splice: &Splice) -> Result<Self> {
+        let mix = with_sample_type(spec, MixFrame)?;
+
+        let mut pieces = Vec::new();
+        let count = splice.segments.len();
+        for (i, segment) in splice.segments.iter().enumerate() {
+            let next = (i + 1 < count).then(|| (&splice.segments[i + 1], splice.overlaps[i + 1]));
+            let fade_out = next.map_or(0, |(_, overlap)| overlap);
+            pieces.push(Piece::Copy(
+                segment.start + splice.overlaps[i]..segment.end - fade_out,
+            ));
+            if let Some((next, frames)) = next
+                && frames > 0
+            {
+                pieces.push(Piece::Crossfade {
+                    from: segment.end - frames,
+                    to: next.start,
+                    frames,
+                });
+            }
+        }
+
+        let sample_len = spec.bits_per_sample as usize / 8;
+        Ok(Self {
+            inner,
+            data_offset,
+            pieces,
+            piece: 0,
+            frame: 0,
+            block_align: sample_len * spec.channels as usize,
+            sample_len,
+            mix,
+            buf: Vec::new(),
+            other: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    /// Reads `frames` frames starting at input frame `start` into `buf`.
+    fn read_frames(&mut self, start: u64, frames: u64, other: bool) -> io::Result<()> {
+        let offset = self.data_offset + start * self.block_align as u64;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let buf = if other {
+            &mut self.other
+        } else {
+            &mut self.buf
+        };
+        buf.resize(frames as usize * self.block_align, 0);
+        self.inner.read_exact(buf)
+    }
+
+    /// Reads the next run of frames of the current piece, mixing them if it is a crossfade.
+    /// Returns `false` once every piece has been read.
+    fn refill(&mut self) -> io::Result<bool> {
+        loop {
+            let (len, start) = match self.pieces.get(self.piece) {
+                None => return Ok(false),
+                Some(Piece::Copy(range)) => (range.end - range.start, range.start),
+                Some(&Piece::Crossfade { from, frames, .. }) => (frames, from),
+            };
+            if self.frame == len {
+                self.piece += 1;
+                self.frame = 0;
+                continue;
+            }
+            let count = CHUNK_FRAMES.min(len - self.frame);
+            self.read_frames(start + self.frame, count, false)?;
+            if let Piece::Crossfade { to, frames, .. } = self.pieces[self.piece] {
+                self.read_frames(to + self.frame, count, true)?;
+                let chunks = self
+                    .buf
+                    .chunks_exact_mut(self.block_align)
+                    .zip(self.other.chunks_exact(self.block_align));
+                for (i, (from, to)) in chunks.enumerate() {
+                    // Neither end of the crossfade is taken whole from one side.
+                    let t = (self.frame + i as u64 + 1) as f64 / (frames + 1) as f64;
+                    (self.mix)(from, to, self.sample_len, t);
+                }
+            }
+            self.frame += count;
+            self.pos = 0;
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SpliceReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() && !self.refill()? {
+            return Ok(0);
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::fixtures::MONO;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_collapse_shortens_pauses_and_maps_positions() {
+        // Pauses of 1000 and 50 frames; only the first is longer than the 100-frame target.
+        let splice = Splice::collapse(100..3000, &[1000..2000, 2500..2550], 100, 20);
+        assert_eq!(splice.segments, vec![100..1060, 1940..3000]);
+        assert_eq!(splice.overlaps, vec![0, 20]);
+        assert_eq!(splice.frames(), 2000);
+        assert_eq!(splice.removed(), 900);
+        assert_eq!(splice.joins(), 1);
+
+        assert_eq!(splice.map(100), Some(0));
+        assert_eq!(splice.map(1040), Some(940));
+        assert_eq!(splice.map(1940), Some(940));
+        assert_eq!(splice.map(3000), Some(2000));
+        assert_eq!(splice.map(1500), None);
+        assert_eq!(splice.clamp(50), 0);
+        assert_eq!(splice.clamp(1200), 960);
+        assert_eq!(splice.clamp(1800), 940);
+        assert_eq!(Splice::new(100..3000).removed(), 0);
+    }
+
+    #[test]
+    fn test_splice_reader_crossfades_joins() {
+        // Ten frames of 1000 followed by ten of 4000 (as if the gap between them was cut).
+        let data: Vec<u8> = [0xAAu8; 4]
+            .into_iter()
+            .chain((0..20).flat_map(|i| if i < 10 { 1000i16 } else { 4000 }.to_le_bytes()))
+            .collect();
+        let splice = Splice {
+            segments: vec![0..10, 10..20],
+            overlaps: vec![0, 3],
+        };
+        let mut reader = SpliceReader::new(Cursor::new(data), 4, MONO, &splice).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        let samples: Vec<i16> = out.chunks_exact(2).map(i16::decode).collect();
+        assert_eq!(samples.len() as u64, splice.frames());
+        assert_eq!(&samples[..7], &[1000; 7]);
+        assert_eq!(&samples[7..10], &[1750, 2500, 3250]);
+        assert_eq!(&samples[10..], &[4000; 7]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Splitting the kept audio of a file into separate segments at silent gaps.
+
+use crate::detect::{self, Detector, FrameSource};
+use crate::report::Row;
+use anyhow::Result;
+use std::ops::Range;
+
+/// Settings for [`split_segments`], with lengths in frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SplitParams {
+    /// Shortest silent gap that separates two segments.
+    pub min_gap: usize,
+    /// Shortest segment; shorter ones are merged into a neighbour.
+    pub min_segment: usize,
+    /// Longest segment; longer ones are cut at their quietest point. `None` for no limit.
+    pub max_segment: Option<usize>,
+    /// Silence kept before each segment, taken from the gap before it.
+    pub pad_start: usize,
+    /// Silence kept after each segment, taken from the gap after it.
+    pub pad_end: usize,
+}
+
+/// Splits `keep` into segments at the silent gaps of at least `params.min_gap` frames found by
+/// [`detect::find_pauses`] with the same detector, threshold and window as edge trimming.
+///
+/// Each segment keeps up to `pad_start`/`pad_end` frames of its neighbouring gaps, but never
+/// more than half a gap, so segments do not overlap. Segments shorter than `min_segment` are
+/// joined to the next one (the last to the previous one), gap included. Segments longer than
+/// `max_segment` are then cut at the quietest point that leaves both pieces at least
+/// `min_segment` and half a window long where possible, so the window measured around a cut
+/// never reaches into the gaps outside the segment.
+pub fn split_segments<S, D>(
+    source: &mut S,
+    detector: &mut D,
+    keep: Range<usize>,
+    threshold_db: f64,
+    window_size: usize,
+    hop_size: usize,
+    params: &SplitParams,
+) -> Result<Vec<Range<usize>>>
+where
+    S: FrameSource + ?Sized,
+    D: Detector<S> + ?Sized,
+{
+    let gaps = detect::find_pauses(
+        source,
+        detector,
+        keep.clone(),
+        threshold_db,
+        window_size,
+        hop_size,
+        params.min_gap.saturating_sub(1),
+    )?;
+
+    let mut segments = Vec::with_capacity(gaps.len() + 1);
+    let mut start = keep.start;
+    for gap in &gaps {
+        let mid = gap.start + gap.len() / 2;
+        segments.push(start..(gap.start + params.pad_end).min(mid));
+        start = gap.end.saturating_sub(params.pad_start).max(mid);
+    }
+    segments.push(start..keep.end);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        match merged.last_mut() {
+            Some(last) if last.len() < params.min_segment => last.end = segment.end,
+            _ => merged.push(segment),
+        }
+    }
+    if merged.len() > 1 && merged[merged.len() - 1].len() < params.min_segment {
+        let last = merged.pop().unwrap_or_default();
+        if let Some(previous) = merged.last_mut() {
+            previous.end = last.end;
+        }
+    }
+
+    let Some(max_segment) = params.max_segment else {
+        return Ok(merged);
+    };
+    let mut split = Vec::with_capacity(merged.len());
+    for mut segment in merged {
+        while segment.len() > max_segment {
+            let min = params
+                .min_segment
+                .max(window_size / 2)
+                .min(max_segment)
+                .max(1);
+            let candidates = segment.start + min
+                ..(segment.start + max_segment + 1).min(segment.end.saturating_sub(min));
+            let cut = if candidates.is_empty() {
+                segment.start + max_segment
+            } else {
+                detect::quietest_frame(source, detector, candidates, window_size, hop_size)?
+            };
+            split.push(segment.start..cut);
+            segment.start = cut;
+        }
+        split.push(segment);
+    }
+    Ok(split)
+}
+
+/// One line of the segment manifest: where a segment file came from in its source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    /// Source file, relative to the input directory.
+    pub source: String,
+    /// Segment file, relative to the output directory.
+    pub segment: String,
+    /// One-based position of the segment in its source.
+    pub index: usize,
+    /// Sample rate of the source in Hz.
+    pub sample_rate: u32,
+    /// Frames of the source in the segment.
+    pub frames: Range<u64>,
+}
+
+impl Row for ManifestEntry {
+    const COLUMNS: &'static [&'static str] = &[
+        "source",
+        "segment",
+        "index",
+        "start_frame",
+        "end_frame",
+        "start_ms",
+        "end_ms",
+        "duration_ms",
+    ];
+
+    fn values(&self) -> Vec<(bool, Option<String>)> {
+        let ms = |frames: u64| {
+            let ms = frames as f64 * 1000.0 / self.sample_rate as f64;
+            (false, Some(format!("{ms:.3}")))
+        };
+        vec![
+            (true, Some(self.source.clone())),
+            (true, Some(self.segment.clone())),
+            (false, Some(self.index.to_string())),
+            (false, Some(self.frames.start.to_string())),
+            (false, Some(self.frames.end.to_string())),
+            ms(self.frames.start),
+            ms(self.frames.end),
+            ms(self.frames.end - self.frames.start),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::ChannelPolicy;
+    use crate::detector::Rms;
+    use crate::source::SliceSource;
+    use crate::source::fixtures::{MONO, bursts};
+
+    fn params() -> SplitParams {
+        SplitParams {
+            min_gap: 400,
+            min_segment: 0,
+            max_segment: None,
+            pad_start: 0,
+            pad_end: 0,
+        }
+    }
+
+    #[test]
+    fn test_splits_at_long_gaps_with_padding() {
+        // Gaps of 1000 and 200 frames; only the first is long enough to split at.
+        let samples = bursts(&[(1000, 1000), (1000, 200), (1000, 0)]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let mut split = |params| {
+            split_segments(
+                &mut source,
+                &mut Rms::default(),
+                0..4200,
+                -50.0,
+                100,
+                20,
+                &params,
+            )
+        };
+
+        assert_eq!(split(params()).unwrap(), vec![0..1000, 2000..4200]);
+        let padded = SplitParams {
+            pad_start: 100,
+            pad_end: 800,
+            ..params()
+        };
+        assert_eq!(split(padded).unwrap(), vec![0..1500, 1900..4200]);
+    }
+
+    #[test]
+    fn test_merges_short_and_cuts_long_segments() {
+        let samples = bursts(&[(300, 500), (2000, 500), (2000, 0)]);
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let merging = SplitParams {
+            min_segment: 500,
+            ..params()
+        };
+        let segments = split_segments(
+            &mut source,
+            &mut Rms::default(),
+            0..5300,
+            -50.0,
+            100,
+            20,
+            &merging,
+        )
+        .unwrap();
+        assert_eq!(segments, vec![0..2800, 3300..5300]);
+
+        // A 3000-frame tone with a quiet dip at 1210..1310 is cut in the dip.
+        let mut samples = bursts(&[(3000, 0)]);
+        for s in &mut samples[1210..1310] {
+            *s /= 10;
+        }
+        let mut source = SliceSource::new(&samples, MONO, ChannelPolicy::Max);
+        let capped = SplitParams {
+            min_segment: 500,
+            max_segment: Some(2000),
+            ..params()
+        };
+        let segments = split_segments(
+            &mut source,
+            &mut Rms::default(),
+            0..3000,
+            -50.0,
+            100,
+            20,
+            &capped,
+        )
+        .unwrap();
+        assert_eq!(segments, vec![0..1260, 1260..3000]);
+    }
+
+    #[test]
+    fn test_manifest_entry_columns() {
+        let entry = ManifestEntry {
+            source: "a/take.wav".into(),
+            segment: "a/take_0002.wav".into(),
+            index: 2,
+            sample_rate: 16_000,
+            frames: 1600..4000,
+        };
+        assert_eq!(
+            entry.to_csv(),
+            "a/take.wav,a/take_0002.wav,2,1600,4000,100.000,250.000,150.000"
+        );
+    }
+}